@@ -0,0 +1,13 @@
+#!/bin/sh
+# Builds the interpreter in release mode and times every benchmark script.
+# Usage: benchmarks/run.sh [interpreter options...]
+set -e
+cd "$(dirname "$0")/.."
+cargo build --release --quiet
+
+for script in benchmarks/*.lox; do
+  start=$(date +%s%N)
+  ./target/release/interpreter-starter-rust run "$script" "$@" > /dev/null
+  end=$(date +%s%N)
+  printf '%-28s %6d ms\n' "$script" $(( (end - start) / 1000000 ))
+done
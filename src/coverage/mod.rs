@@ -0,0 +1,208 @@
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, fs, io, rc::Rc};
+
+use crate::environment::Environment;
+use crate::expression::Expression;
+use crate::module::ModuleLoader;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_hook::{RuntimeHook, RuntimeHooks};
+use crate::statement::Statement;
+use crate::token::Token;
+
+struct BranchSite {
+  line: usize,
+  // Position among the branch sites of the same line; the lcov "block" number.
+  block: usize,
+  taken: [u64; 2]
+}
+
+struct CoverageData {
+  lines: BTreeMap<usize, u64>,
+  sites: HashMap<usize, BranchSite>
+}
+
+// Line and branch coverage. Every statement and branch of the program is registered up front so that code that never
+// ran is reported too. Clones share the same data.
+#[derive(Clone)]
+pub struct Coverage {
+  data: Rc<RefCell<CoverageData>>
+}
+
+impl Coverage {
+  pub fn new(statements: &[Statement]) -> Self {
+    let mut data = CoverageData { lines: BTreeMap::new(), sites: HashMap::new() };
+
+    for statement in statements {
+      data.register_statement(statement);
+    }
+
+    Self { data: Rc::new(RefCell::new(data)) }
+  }
+
+  pub fn summary(&self) -> String {
+    let data = self.data.borrow();
+    let (lines_hit, lines_found) = data.line_totals();
+    let (branches_hit, branches_found) = data.branch_totals();
+
+    let uncovered: Vec<String> = data.lines.iter()
+      .filter(|(_line, hits)| **hits == 0)
+      .map(|(line, _hits)| line.to_string())
+      .collect();
+
+    let mut summary = format!(
+      "Lines: {}/{} ({})\nBranches: {}/{} ({})",
+      lines_hit, lines_found, Coverage::percent(lines_hit, lines_found),
+      branches_hit, branches_found, Coverage::percent(branches_hit, branches_found)
+    );
+
+    if !uncovered.is_empty() {
+      summary.push_str(&format!("\nUncovered lines: {}", uncovered.join(", ")));
+    }
+
+    summary
+  }
+
+  pub fn write_lcov(&self, source_path: &str, output_path: &str) -> io::Result<()> {
+    let data = self.data.borrow();
+    let mut output = format!("TN:\nSF:{}\n", source_path);
+
+    let mut sites: Vec<&BranchSite> = data.sites.values().collect();
+    sites.sort_by_key(|site| (site.line, site.block));
+
+    for site in &sites {
+      let executed = site.taken[0] + site.taken[1] > 0;
+      for (branch, taken) in site.taken.iter().enumerate() {
+        let taken = if executed { taken.to_string() } else { String::from("-") };
+        output.push_str(&format!("BRDA:{},{},{},{}\n", site.line, site.block, branch, taken));
+      }
+    }
+
+    let (branches_hit, branches_found) = data.branch_totals();
+    output.push_str(&format!("BRF:{}\nBRH:{}\n", branches_found, branches_hit));
+
+    for (line, hits) in &data.lines {
+      output.push_str(&format!("DA:{},{}\n", line, hits));
+    }
+
+    let (lines_hit, lines_found) = data.line_totals();
+    output.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", lines_found, lines_hit));
+
+    fs::write(output_path, output)
+  }
+
+  fn percent(hit: usize, found: usize) -> String {
+    if found == 0 {
+      return String::from("n/a");
+    }
+
+    format!("{:.1}%", hit as f64 * 100. / found as f64)
+  }
+}
+
+impl CoverageData {
+  fn line_totals(&self) -> (usize, usize) {
+    (self.lines.values().filter(|hits| **hits > 0).count(), self.lines.len())
+  }
+
+  fn branch_totals(&self) -> (usize, usize) {
+    let hit = self.sites.values().map(|site| site.taken.iter().filter(|t| **t > 0).count()).sum();
+    (hit, self.sites.len() * 2)
+  }
+
+  fn register_statement(&mut self, statement: &Statement) {
+    if let Some(line) = statement.line() {
+      self.lines.entry(line).or_insert(0);
+    }
+
+    match statement {
+      Statement::Print(expr) | Statement::Expression(expr) | Statement::Var(_, expr) | Statement::Return(expr) => self.register_expression(expr),
+      Statement::Function(_name, _args, body, _captures) => self.register_statement(body),
+      Statement::Block(statements) => {
+        for statement in statements.iter() {
+          self.register_statement(statement);
+        }
+      },
+      Statement::If(condition, then_stmt, else_stmt) => {
+        self.register_site(condition.token());
+        self.register_expression(condition);
+        self.register_statement(then_stmt);
+        self.register_statement(else_stmt);
+      },
+      Statement::While(condition, body) => {
+        self.register_site(condition.token());
+        self.register_expression(condition);
+        self.register_statement(body);
+      },
+      Statement::For(initializer, condition, increment, body) => {
+        self.register_statement(initializer);
+        self.register_site(condition.token());
+        self.register_expression(condition);
+        self.register_expression(increment);
+        self.register_statement(body);
+      },
+      Statement::Empty() | Statement::Native(_) | Statement::Import(_, _) | Statement::ImportFrom(_, _) => {}
+    }
+  }
+
+  fn register_expression(&mut self, expression: &Expression) {
+    match expression {
+      Expression::Logical(left, operator, right) => {
+        self.register_expression(left);
+        self.register_site(Some(operator));
+        self.register_expression(right);
+      },
+      Expression::Conditional(condition, question, then_expr, else_expr) => {
+        self.register_expression(condition);
+        self.register_site(Some(question));
+        self.register_expression(then_expr);
+        self.register_expression(else_expr);
+      },
+      Expression::Binary(left, _token, right) => {
+        self.register_expression(left);
+        self.register_expression(right);
+      },
+      Expression::Call(callee, arguments) => {
+        self.register_expression(callee);
+        for argument in arguments {
+          self.register_expression(argument);
+        }
+      },
+      Expression::Interpolation(_token, parts) => {
+        for part in parts {
+          self.register_expression(part);
+        }
+      },
+      Expression::Unary(_, expr) | Expression::Grouping(expr) | Expression::Assign(_, expr, _) | Expression::Get(expr, _) => self.register_expression(expr),
+      Expression::Literal(_, _) | Expression::Identifier(_, _) | Expression::Update(_, _, _, _) | Expression::Nil() => {}
+    }
+  }
+
+  fn register_site(&mut self, token: Option<&Rc<Token>>) {
+    let token = match token {
+      Some(token) => token,
+      None => return
+    };
+
+    let block = self.sites.values().filter(|site| site.line == token.line).count();
+    self.sites.entry(RuntimeHooks::site(token)).or_insert(BranchSite { line: token.line, block, taken: [0, 0] });
+  }
+}
+
+impl RuntimeHook for Coverage {
+  fn before_statement(&mut self, _statement: &Statement, line: usize, _env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    // Only the lines of the program itself are reported, imported modules can have statements on the same lines.
+    if !ModuleLoader::in_main_file() {
+      return Ok(());
+    }
+
+    if let Some(hits) = self.data.borrow_mut().lines.get_mut(&line) {
+      *hits += 1;
+    }
+    Ok(())
+  }
+
+  fn on_branch(&mut self, site: usize, _line: usize, branch: usize) {
+    if let Some(site) = self.data.borrow_mut().sites.get_mut(&site) {
+      site.taken[branch] += 1;
+    }
+  }
+}
@@ -0,0 +1,127 @@
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use super::json::Json;
+
+// Content-Length framed JSON messages over stdio. Requests are read on a separate thread so they can be polled while the program runs.
+pub struct DapConnection {
+  seq: i64,
+  requests: Receiver<Json>
+}
+
+impl DapConnection {
+  pub fn open() -> Self {
+    let (sender, requests) = mpsc::channel();
+
+    thread::spawn(move || {
+      let stdin = io::stdin();
+      let mut reader = stdin.lock();
+
+      while let Some(message) = DapConnection::read_message(&mut reader) {
+        if sender.send(message).is_err() {
+          break;
+        }
+      }
+    });
+
+    Self { seq: 1, requests }
+  }
+
+  // Blocks until the next request. None means the client went away.
+  pub fn receive(&self) -> Option<Json> {
+    self.requests.recv().ok()
+  }
+
+  pub fn try_receive(&self) -> Result<Option<Json>, ()> {
+    match self.requests.try_recv() {
+      Ok(message) => Ok(Some(message)),
+      Err(TryRecvError::Empty) => Ok(None),
+      Err(TryRecvError::Disconnected) => Err(())
+    }
+  }
+
+  pub fn respond(&mut self, request: &Json, body: Json) {
+    let message = Json::object(vec![
+      ("type", Json::str("response")),
+      ("request_seq", request.get("seq").cloned().unwrap_or(Json::Number(0.))),
+      ("success", Json::Bool(true)),
+      ("command", request.get("command").cloned().unwrap_or(Json::str(""))),
+      ("body", body)
+    ]);
+    self.send(message);
+  }
+
+  pub fn respond_error(&mut self, request: &Json, error: &str) {
+    let message = Json::object(vec![
+      ("type", Json::str("response")),
+      ("request_seq", request.get("seq").cloned().unwrap_or(Json::Number(0.))),
+      ("success", Json::Bool(false)),
+      ("command", request.get("command").cloned().unwrap_or(Json::str(""))),
+      ("message", Json::str(error))
+    ]);
+    self.send(message);
+  }
+
+  pub fn event(&mut self, event: &str, body: Json) {
+    let message = Json::object(vec![
+      ("type", Json::str("event")),
+      ("event", Json::str(event)),
+      ("body", body)
+    ]);
+    self.send(message);
+  }
+
+  fn send(&mut self, message: Json) {
+    let message = match message {
+      Json::Object(mut pairs) => {
+        pairs.insert(0, (String::from("seq"), Json::Number(self.seq as f64)));
+        Json::Object(pairs)
+      },
+      other => other
+    };
+    self.seq += 1;
+
+    let content = message.to_string();
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "Content-Length: {}\r\n\r\n{}", content.len(), content).unwrap();
+    stdout.flush().unwrap();
+  }
+
+  fn read_message(reader: &mut impl BufRead) -> Option<Json> {
+    loop {
+      let mut content_length: Option<usize> = None;
+
+      loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+          return None;
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+          break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+          if name.trim().eq_ignore_ascii_case("Content-Length") {
+            content_length = value.trim().parse().ok();
+          }
+        }
+      }
+
+      let length = match content_length {
+        Some(length) => length,
+        None => continue
+      };
+
+      let mut content = vec![0; length];
+      reader.read_exact(&mut content).ok()?;
+
+      match Json::parse(&String::from_utf8_lossy(&content)) {
+        Ok(message) => return Some(message),
+        Err(e) => eprintln!("Ignoring malformed DAP message: {}", e)
+      }
+    }
+  }
+}
@@ -0,0 +1,268 @@
+use std::fmt;
+
+// Minimal JSON value used for Debug Adapter Protocol messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+  Null(),
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>)
+}
+
+impl Json {
+  pub fn object(pairs: Vec<(&str, Json)>) -> Json {
+    Json::Object(pairs.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+  }
+
+  pub fn str(value: &str) -> Json {
+    Json::String(value.to_owned())
+  }
+
+  pub fn get(&self, key: &str) -> Option<&Json> {
+    match self {
+      Json::Object(pairs) => pairs.iter().find(|(k, _v)| k == key).map(|(_k, v)| v),
+      _ => None
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Json::String(s) => Some(s),
+      _ => None
+    }
+  }
+
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Json::Number(n) => Some(*n),
+      _ => None
+    }
+  }
+
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Json::Bool(b) => Some(*b),
+      _ => None
+    }
+  }
+
+  pub fn as_array(&self) -> Option<&Vec<Json>> {
+    match self {
+      Json::Array(items) => Some(items),
+      _ => None
+    }
+  }
+
+  fn encode(&self) -> String {
+    match self {
+      Json::Null() => String::from("null"),
+      Json::Bool(b) => b.to_string(),
+      Json::Number(n) => if n.is_finite() { n.to_string() } else { String::from("null") },
+      Json::String(s) => Json::quote(s),
+      Json::Array(items) => format!("[{}]", items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(",")),
+      Json::Object(pairs) => format!("{{{}}}", pairs.iter().map(|(k, v)| format!("{}:{}", Json::quote(k), v)).collect::<Vec<_>>().join(","))
+    }
+  }
+
+  fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+      match c {
+        '"' => quoted.push_str("\\\""),
+        '\\' => quoted.push_str("\\\\"),
+        '\n' => quoted.push_str("\\n"),
+        '\r' => quoted.push_str("\\r"),
+        '\t' => quoted.push_str("\\t"),
+        c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+        c => quoted.push(c)
+      }
+    }
+
+    quoted.push('"');
+    quoted
+  }
+
+  pub fn parse(input: &str) -> Result<Json, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut index = 0;
+
+    let value = Json::parse_value(&chars, &mut index)?;
+    Json::skip_whitespace(&chars, &mut index);
+
+    if index < chars.len() {
+      return Err(format!("Unexpected trailing character at {}", index));
+    }
+
+    Ok(value)
+  }
+
+  fn parse_value(chars: &[char], index: &mut usize) -> Result<Json, String> {
+    Json::skip_whitespace(chars, index);
+
+    match chars.get(*index) {
+      Some('n') => Json::parse_keyword(chars, index, "null", Json::Null()),
+      Some('t') => Json::parse_keyword(chars, index, "true", Json::Bool(true)),
+      Some('f') => Json::parse_keyword(chars, index, "false", Json::Bool(false)),
+      Some('"') => Json::parse_string(chars, index).map(Json::String),
+      Some('[') => {
+        *index += 1;
+        let mut items = Vec::new();
+
+        Json::skip_whitespace(chars, index);
+        if chars.get(*index) == Some(&']') {
+          *index += 1;
+          return Ok(Json::Array(items));
+        }
+
+        loop {
+          items.push(Json::parse_value(chars, index)?);
+          Json::skip_whitespace(chars, index);
+
+          match chars.get(*index) {
+            Some(',') => *index += 1,
+            Some(']') => {
+              *index += 1;
+              return Ok(Json::Array(items));
+            },
+            _ => return Err(format!("Expected ',' or ']' at {}", index))
+          }
+        }
+      },
+      Some('{') => {
+        *index += 1;
+        let mut pairs = Vec::new();
+
+        Json::skip_whitespace(chars, index);
+        if chars.get(*index) == Some(&'}') {
+          *index += 1;
+          return Ok(Json::Object(pairs));
+        }
+
+        loop {
+          Json::skip_whitespace(chars, index);
+          let key = Json::parse_string(chars, index)?;
+
+          Json::skip_whitespace(chars, index);
+          if chars.get(*index) != Some(&':') {
+            return Err(format!("Expected ':' at {}", index));
+          }
+          *index += 1;
+
+          pairs.push((key, Json::parse_value(chars, index)?));
+          Json::skip_whitespace(chars, index);
+
+          match chars.get(*index) {
+            Some(',') => *index += 1,
+            Some('}') => {
+              *index += 1;
+              return Ok(Json::Object(pairs));
+            },
+            _ => return Err(format!("Expected ',' or '}}' at {}", index))
+          }
+        }
+      },
+      Some(c) if *c == '-' || c.is_ascii_digit() => {
+        let start = *index;
+        while *index < chars.len() && (chars[*index].is_ascii_digit() || "+-.eE".contains(chars[*index])) {
+          *index += 1;
+        }
+
+        let text: String = chars[start..*index].iter().collect();
+        match text.parse::<f64>() {
+          Ok(n) => Ok(Json::Number(n)),
+          Err(_) => Err(format!("Invalid number '{}'", text))
+        }
+      },
+      Some(c) => Err(format!("Unexpected character '{}' at {}", c, index)),
+      None => Err(String::from("Unexpected end of input"))
+    }
+  }
+
+  fn parse_keyword(chars: &[char], index: &mut usize, keyword: &str, value: Json) -> Result<Json, String> {
+    let end = *index + keyword.len();
+
+    if end <= chars.len() && chars[*index..end].iter().collect::<String>() == keyword {
+      *index = end;
+      Ok(value)
+    } else {
+      Err(format!("Unexpected token at {}", index))
+    }
+  }
+
+  fn parse_string(chars: &[char], index: &mut usize) -> Result<String, String> {
+    if chars.get(*index) != Some(&'"') {
+      return Err(format!("Expected string at {}", index));
+    }
+    *index += 1;
+
+    let mut result = String::new();
+    while *index < chars.len() {
+      let c = chars[*index];
+      *index += 1;
+
+      match c {
+        '"' => return Ok(result),
+        '\\' => {
+          let escaped = match chars.get(*index) {
+            Some(e) => *e,
+            None => break
+          };
+          *index += 1;
+
+          match escaped {
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            't' => result.push('\t'),
+            'b' => result.push('\u{8}'),
+            'f' => result.push('\u{c}'),
+            'u' => {
+              let mut code = Json::parse_hex4(chars, index)?;
+
+              // Characters outside the BMP arrive as a surrogate pair.
+              if (0xD800..0xDC00).contains(&code) && chars.get(*index) == Some(&'\\') && chars.get(*index + 1) == Some(&'u') {
+                *index += 2;
+                let low = Json::parse_hex4(chars, index)?;
+                if !(0xDC00..0xE000).contains(&low) {
+                  return Err(String::from("Invalid surrogate pair"));
+                }
+                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+              }
+
+              result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+            },
+            other => result.push(other)
+          }
+        },
+        c => result.push(c)
+      }
+    }
+
+    Err(String::from("Unterminated string"))
+  }
+
+  fn parse_hex4(chars: &[char], index: &mut usize) -> Result<u32, String> {
+    if *index + 4 > chars.len() {
+      return Err(String::from("Invalid unicode escape"));
+    }
+
+    let hex: String = chars[*index..*index + 4].iter().collect();
+    *index += 4;
+
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("Invalid unicode escape '{}'", hex))
+  }
+
+  fn skip_whitespace(chars: &[char], index: &mut usize) {
+    while *index < chars.len() && chars[*index].is_whitespace() {
+      *index += 1;
+    }
+  }
+}
+
+impl fmt::Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.encode())
+  }
+}
@@ -0,0 +1,473 @@
+pub mod dap_connection;
+pub mod json;
+
+use std::{cell::RefCell, collections::HashSet, fs, path::PathBuf, process, rc::Rc};
+
+use dap_connection::DapConnection;
+use json::Json;
+
+use crate::debugger::stepper::{Location, StepMode, StopReason, Stepper};
+use crate::environment::Environment;
+use crate::expression::{evaluator::ExprEvaluator, Expression};
+use crate::module::ModuleLoader;
+use crate::optimizer::Optimizer;
+use crate::parser::Parser;
+use crate::runtime::call_stack::CallStack;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_hook::{RuntimeHook, RuntimeHooks};
+use crate::runtime::runtime_type::RuntimeType;
+use crate::statement::{evaluator::StmtEvaluator, Statement};
+use crate::token::Token;
+
+const THREAD_ID: f64 = 1.;
+
+struct StackFrame {
+  name: String,
+  location: Location,
+  env: Rc<RefCell<Environment>>
+}
+
+// Debug Adapter Protocol server over stdio. Program output is forwarded as `output` events since stdout carries the protocol.
+pub struct DapServer {
+  connection: Rc<RefCell<DapConnection>>,
+  program: String,
+  stop_on_entry: bool,
+  stepper: Stepper,
+  pause_requested: bool,
+  // Valid only while stopped; ids are indexes (plus one for variable references).
+  frames: Vec<StackFrame>,
+  variables: Vec<Vec<(String, Rc<RuntimeType>)>>
+}
+
+impl DapServer {
+  fn new(connection: Rc<RefCell<DapConnection>>) -> Self {
+    Self {
+      connection,
+      program: String::new(),
+      stop_on_entry: false,
+      stepper: Stepper::new(StepMode::Continue),
+      pause_requested: false,
+      frames: Vec::new(),
+      variables: Vec::new()
+    }
+  }
+
+  pub fn serve(env: &Rc<RefCell<Environment>>) {
+    let connection = Rc::new(RefCell::new(DapConnection::open()));
+    let mut server = DapServer::new(connection.clone());
+
+    if !server.configure() {
+      return;
+    }
+
+    let statements = match server.load_program() {
+      Ok(statements) => statements,
+      Err(message) => {
+        DapServer::output(&connection, "stderr", &message);
+        DapServer::finish(&connection, 65);
+        return;
+      }
+    };
+
+    // Stepping follows the source, so nothing is folded away, in the program or the modules it imports.
+    Optimizer::set_level(0);
+
+    if server.stop_on_entry {
+      server.stepper.resume(StepMode::StepIn);
+    }
+    let program = server.program.clone();
+    RuntimeHooks::install(Box::new(server));
+
+    // Relative imports resolve against the program's directory, not the adapter's.
+    ModuleLoader::push_file(&program);
+    let mut exit_code = 0;
+    for statement in &statements {
+      match StmtEvaluator::evaluate(statement, env) {
+        Ok(_) => {},
+        Err(RuntimeError::Exit(code)) => {
+          exit_code = code;
+          break;
+        },
+        Err(e) => {
+          DapServer::output(&connection, "stderr", &e.to_string());
+          exit_code = 70;
+          break;
+        }
+      }
+    }
+    ModuleLoader::pop_file();
+
+    DapServer::finish(&connection, exit_code);
+  }
+
+  // Handles the initialization sequence. Returns false if the client disconnected before launching.
+  fn configure(&mut self) -> bool {
+    let mut launched = false;
+    let mut configured = false;
+
+    while !(launched && configured) {
+      let request = match self.connection.borrow().receive() {
+        Some(request) => request,
+        None => return false
+      };
+
+      match DapServer::command(&request) {
+        "initialize" => {
+          let capabilities = Json::object(vec![
+            ("supportsConfigurationDoneRequest", Json::Bool(true)),
+            ("supportsEvaluateForHovers", Json::Bool(true))
+          ]);
+          let mut connection = self.connection.borrow_mut();
+          connection.respond(&request, capabilities);
+          connection.event("initialized", Json::object(vec![]));
+        },
+        "launch" => {
+          let arguments = request.get("arguments");
+          match arguments.and_then(|a| a.get("program")).and_then(|p| p.as_str()) {
+            Some(program) => {
+              self.program = program.to_owned();
+              self.stop_on_entry = arguments.and_then(|a| a.get("stopOnEntry")).and_then(|s| s.as_bool()).unwrap_or(false);
+              self.connection.borrow_mut().respond(&request, Json::object(vec![]));
+              launched = true;
+            },
+            None => self.connection.borrow_mut().respond_error(&request, "Missing 'program' launch argument.")
+          }
+        },
+        "configurationDone" => {
+          self.connection.borrow_mut().respond(&request, Json::object(vec![]));
+          configured = true;
+        },
+        _ => self.handle_common(&request)
+      }
+    }
+
+    true
+  }
+
+  fn load_program(&self) -> Result<Vec<Statement>, String> {
+    let source = match fs::read_to_string(&self.program) {
+      Ok(source) => source,
+      Err(_) => return Err(format!("Failed to read file {}", self.program))
+    };
+
+    let (tokens, errors) = Token::tokenize(&source);
+    if !errors.is_empty() {
+      return Err(errors.join("\n"));
+    }
+
+    Parser::parse_statements(&tokens).map_err(|e| e.to_string())
+  }
+
+  fn finish(connection: &Rc<RefCell<DapConnection>>, exit_code: i32) {
+    let mut connection = connection.borrow_mut();
+    connection.event("exited", Json::object(vec![("exitCode", Json::Number(exit_code as f64))]));
+    connection.event("terminated", Json::object(vec![]));
+
+    while let Some(request) = connection.receive() {
+      if DapServer::command(&request) == "disconnect" {
+        connection.respond(&request, Json::object(vec![]));
+        return;
+      }
+      connection.respond_error(&request, "The program has terminated.");
+    }
+  }
+
+  fn output(connection: &Rc<RefCell<DapConnection>>, category: &str, text: &str) {
+    connection.borrow_mut().event("output", Json::object(vec![
+      ("category", Json::str(category)),
+      ("output", Json::String(format!("{}\n", text)))
+    ]));
+  }
+
+  fn command(request: &Json) -> &str {
+    request.get("command").and_then(|c| c.as_str()).unwrap_or("")
+  }
+
+  // Requests that are valid whether or not the program is stopped.
+  fn handle_common(&mut self, request: &Json) {
+    let mut connection = self.connection.borrow_mut();
+
+    match DapServer::command(request) {
+      "setBreakpoints" => {
+        let arguments = request.get("arguments");
+        // Breakpoints are set per file; a request without a source is about the program.
+        let path = arguments.and_then(|a| a.get("source")).and_then(|s| s.get("path")).and_then(|p| p.as_str()).unwrap_or(&self.program);
+        let file = Some(Rc::new(fs::canonicalize(path).unwrap_or(PathBuf::from(path))));
+        let lines: Vec<usize> = arguments
+          .and_then(|a| a.get("breakpoints"))
+          .and_then(|b| b.as_array())
+          .map(|b| b.iter().filter_map(|bp| bp.get("line").and_then(|l| l.as_f64())).map(|l| l as usize).collect())
+          .unwrap_or_default();
+
+        self.stepper.breakpoints.retain(|(f, _line)| *f != file);
+        self.stepper.breakpoints.extend(lines.iter().map(|l| (file.clone(), *l)));
+
+        let breakpoints = lines.iter()
+          .map(|l| Json::object(vec![("verified", Json::Bool(true)), ("line", Json::Number(*l as f64))]))
+          .collect();
+        connection.respond(request, Json::object(vec![("breakpoints", Json::Array(breakpoints))]));
+      },
+      "setExceptionBreakpoints" => connection.respond(request, Json::object(vec![])),
+      "threads" => {
+        let thread = Json::object(vec![("id", Json::Number(THREAD_ID)), ("name", Json::str("main"))]);
+        connection.respond(request, Json::object(vec![("threads", Json::Array(vec![thread]))]));
+      },
+      "disconnect" | "terminate" => {
+        connection.respond(request, Json::object(vec![]));
+        process::exit(0);
+      },
+      command => connection.respond_error(request, &format!("Unsupported request '{}'.", command))
+    }
+  }
+
+  fn stop(&mut self, reason: &str, location: Location, env: &Rc<RefCell<Environment>>) {
+    let depth = CallStack::depth();
+    self.stepper.stopped(location.clone(), depth);
+    self.pause_requested = false;
+    self.collect_frames(location, env);
+
+    self.connection.borrow_mut().event("stopped", Json::object(vec![
+      ("reason", Json::str(reason)),
+      ("threadId", Json::Number(THREAD_ID)),
+      ("allThreadsStopped", Json::Bool(true))
+    ]));
+
+    loop {
+      let request = match self.connection.borrow().receive() {
+        Some(request) => request,
+        None => process::exit(0)
+      };
+
+      let mode = match DapServer::command(&request) {
+        "continue" => Some(StepMode::Continue),
+        "next" => Some(StepMode::StepOver(depth)),
+        "stepIn" => Some(StepMode::StepIn),
+        "stepOut" => Some(StepMode::StepOut(depth)),
+        _ => None
+      };
+
+      if let Some(mode) = mode {
+        self.connection.borrow_mut().respond(&request, Json::object(vec![("allThreadsContinued", Json::Bool(true))]));
+        self.stepper.resume(mode);
+        self.frames.clear();
+        self.variables.clear();
+        return;
+      }
+
+      match DapServer::command(&request) {
+        "stackTrace" => self.stack_trace(&request),
+        "scopes" => self.scopes(&request),
+        "variables" => self.variables(&request),
+        "evaluate" => self.evaluate(&request),
+        "pause" => self.connection.borrow_mut().respond(&request, Json::object(vec![])),
+        _ => self.handle_common(&request)
+      }
+    }
+  }
+
+  fn collect_frames(&mut self, location: Location, env: &Rc<RefCell<Environment>>) {
+    let mut location = location;
+    let mut env = env.clone();
+
+    self.frames.clear();
+    for frame in CallStack::frames() {
+      self.frames.push(StackFrame { name: frame.fun_name.clone(), location, env });
+      location = (frame.caller_file.clone(), frame.call_line);
+      env = frame.caller_env.clone();
+    }
+    self.frames.push(StackFrame { name: String::from("<script>"), location, env });
+  }
+
+  fn frame_argument(&self, request: &Json) -> Option<&StackFrame> {
+    let id = request.get("arguments").and_then(|a| a.get("frameId")).and_then(|f| f.as_f64()).unwrap_or(0.);
+    self.frames.get(id as usize)
+  }
+
+  fn stack_trace(&mut self, request: &Json) {
+    let source = |file: &Option<Rc<PathBuf>>| match file {
+      Some(file) => Json::object(vec![("name", Json::String(ModuleLoader::display(file))), ("path", Json::String(file.display().to_string()))]),
+      None => Json::object(vec![("name", Json::str(&self.program)), ("path", Json::str(&self.program))])
+    };
+
+    let frames: Vec<Json> = self.frames.iter().enumerate()
+      .map(|(i, frame)| Json::object(vec![
+        ("id", Json::Number(i as f64)),
+        ("name", Json::str(&frame.name)),
+        ("line", Json::Number(frame.location.1 as f64)),
+        ("column", Json::Number(1.)),
+        ("source", source(&frame.location.0))
+      ]))
+      .collect();
+
+    let total = frames.len() as f64;
+    self.connection.borrow_mut().respond(request, Json::object(vec![
+      ("stackFrames", Json::Array(frames)),
+      ("totalFrames", Json::Number(total))
+    ]));
+  }
+
+  fn scopes(&mut self, request: &Json) {
+    let env = match self.frame_argument(request) {
+      Some(frame) => frame.env.clone(),
+      None => return self.connection.borrow_mut().respond_error(request, "Unknown frame.")
+    };
+
+    // Nested block scopes are merged into one "Locals" scope, inner bindings shadowing outer ones.
+    let mut locals: Vec<(String, Rc<RuntimeType>)> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut scope = env;
+
+    loop {
+      let enclosing = match scope.borrow().enclosing() {
+        Some(enclosing) => enclosing,
+        None => break
+      };
+
+      for (name, value) in scope.borrow().bindings() {
+        if seen.insert(name.clone()) {
+          locals.push((name, value));
+        }
+      }
+      scope = enclosing;
+    }
+    locals.sort_by(|a, b| a.0.cmp(&b.0));
+    let globals = scope.borrow().bindings();
+
+    self.variables.push(locals);
+    let locals_ref = self.variables.len();
+    self.variables.push(globals);
+    let globals_ref = self.variables.len();
+
+    let scope = |name: &str, reference: usize| Json::object(vec![
+      ("name", Json::str(name)),
+      ("variablesReference", Json::Number(reference as f64)),
+      ("expensive", Json::Bool(false))
+    ]);
+
+    self.connection.borrow_mut().respond(request, Json::object(vec![
+      ("scopes", Json::Array(vec![scope("Locals", locals_ref), scope("Globals", globals_ref)]))
+    ]));
+  }
+
+  fn variables(&mut self, request: &Json) {
+    let reference = request.get("arguments").and_then(|a| a.get("variablesReference")).and_then(|r| r.as_f64()).unwrap_or(0.) as usize;
+
+    let bindings = reference.checked_sub(1).and_then(|i| self.variables.get(i)).cloned().unwrap_or_default();
+    let variables: Vec<Json> = bindings.iter()
+      .map(|(name, value)| Json::object(vec![
+        ("name", Json::str(name)),
+        ("value", Json::String(value.to_string())),
+        ("type", Json::str(value.type_name())),
+        ("variablesReference", Json::Number(self.children_reference(value) as f64))
+      ]))
+      .collect();
+
+    self.connection.borrow_mut().respond(request, Json::object(vec![("variables", Json::Array(variables))]));
+  }
+
+  fn evaluate(&mut self, request: &Json) {
+    let text = request.get("arguments").and_then(|a| a.get("expression")).and_then(|e| e.as_str()).unwrap_or("").to_owned();
+    let env = match self.frame_argument(request) {
+      Some(frame) => frame.env.clone(),
+      None => return self.connection.borrow_mut().respond_error(request, "Unknown frame.")
+    };
+
+    let (tokens, errors) = Token::tokenize(&text);
+    if !errors.is_empty() {
+      return self.connection.borrow_mut().respond_error(request, &errors.join("\n"));
+    }
+
+    let result = match Parser::parse_expression(&tokens) {
+      // Calls would run statements from inside the hook and assignments would change the paused program, so only
+      // side-effect free lookups are allowed.
+      Ok(expression) if DapServer::has_side_effects(&expression) => Err(String::from("Function calls and assignments are not supported while debugging.")),
+      Ok(expression) => ExprEvaluator::evaluate(&expression, &env).map_err(|e| e.to_string()),
+      Err(e) => Err(e.to_string())
+    };
+
+    match result {
+      Ok(value) => {
+        let reference = self.children_reference(&value);
+        self.connection.borrow_mut().respond(request, Json::object(vec![
+          ("result", Json::String(value.to_string())),
+          ("type", Json::str(value.type_name())),
+          ("variablesReference", Json::Number(reference as f64))
+        ]))
+      },
+      Err(message) => self.connection.borrow_mut().respond_error(request, &message)
+    }
+  }
+
+  // Lists and modules can be expanded in the client: their elements or exports get a reference of their own. 0 for
+  // other values.
+  fn children_reference(&mut self, value: &RuntimeType) -> usize {
+    let children: Vec<(String, Rc<RuntimeType>)> = match value {
+      RuntimeType::List(list) => list.borrow().iter().enumerate().map(|(i, v)| (format!("[{}]", i), Rc::new(v.clone()))).collect(),
+      RuntimeType::Module(module) => module.get_env().borrow().bindings(),
+      _ => return 0
+    };
+
+    self.variables.push(children);
+    self.variables.len()
+  }
+
+  fn has_side_effects(expression: &Expression) -> bool {
+    match expression {
+      Expression::Call(_, _) | Expression::Assign(_, _, _) | Expression::Update(_, _, _, _) => true,
+      Expression::Unary(_token, right) => DapServer::has_side_effects(right),
+      Expression::Binary(left, _token, right) | Expression::Logical(left, _token, right) => DapServer::has_side_effects(left) || DapServer::has_side_effects(right),
+      Expression::Grouping(expr) | Expression::Get(expr, _) => DapServer::has_side_effects(expr),
+      Expression::Conditional(condition, _question, then_expr, else_expr) => {
+        DapServer::has_side_effects(condition) || DapServer::has_side_effects(then_expr) || DapServer::has_side_effects(else_expr)
+      },
+      Expression::Interpolation(_token, parts) => parts.iter().any(DapServer::has_side_effects),
+      Expression::Literal(_, _) | Expression::Identifier(_, _) | Expression::Nil() => false
+    }
+  }
+}
+
+impl RuntimeHook for DapServer {
+  fn before_statement(&mut self, _statement: &Statement, line: usize, env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    // Serve requests that arrived while the program was running.
+    loop {
+      let pending = self.connection.borrow().try_receive();
+      match pending {
+        Ok(Some(request)) => match DapServer::command(&request) {
+          "pause" => {
+            self.connection.borrow_mut().respond(&request, Json::object(vec![]));
+            self.pause_requested = true;
+          },
+          "setBreakpoints" | "setExceptionBreakpoints" | "threads" | "disconnect" | "terminate" => self.handle_common(&request),
+          _ => self.connection.borrow_mut().respond_error(&request, "The program is running.")
+        },
+        Ok(None) => break,
+        Err(()) => process::exit(0)
+      }
+    }
+
+    let depth = CallStack::depth();
+    let location = (ModuleLoader::current_file(), line);
+    let reason = if self.pause_requested {
+      Some("pause")
+    } else {
+      match self.stepper.should_stop(&location, depth) {
+        Some(StopReason::Breakpoint) => Some("breakpoint"),
+        Some(StopReason::Step) => Some(if self.stop_on_entry { "entry" } else { "step" }),
+        None => None
+      }
+    };
+
+    if let Some(reason) = reason {
+      self.stop_on_entry = false;
+      self.stop(reason, location.clone(), env);
+    }
+
+    self.stepper.visit(location, depth);
+    Ok(())
+  }
+
+  fn on_output(&mut self, text: &str) -> bool {
+    DapServer::output(&self.connection, "stdout", text);
+    true
+  }
+}
@@ -0,0 +1,76 @@
+pub enum DebuggerCommand {
+  // An optional file, relative to the program's directory, and a line.
+  Break(Option<String>, usize),
+  Delete(Option<String>, usize),
+  Breakpoints(),
+  Continue(),
+  StepIn(),
+  StepOver(),
+  StepOut(),
+  Backtrace(),
+  Vars(),
+  Print(String),
+  List(),
+  Help(),
+  Quit()
+}
+
+impl DebuggerCommand {
+  pub fn parse(input: &str) -> Result<DebuggerCommand, String> {
+    let mut parts = input.split_whitespace();
+    let name = match parts.next() {
+      Some(name) => name,
+      None => return Err(String::from("Empty command. Type 'help' for a list of commands."))
+    };
+    let argument = parts.next();
+
+    match name {
+      "b" | "break" => DebuggerCommand::location_argument(argument).map(|(file, line)| DebuggerCommand::Break(file, line)),
+      "d" | "delete" => DebuggerCommand::location_argument(argument).map(|(file, line)| DebuggerCommand::Delete(file, line)),
+      "bl" | "breakpoints" => Ok(DebuggerCommand::Breakpoints()),
+      "c" | "continue" => Ok(DebuggerCommand::Continue()),
+      "s" | "step" => Ok(DebuggerCommand::StepIn()),
+      "n" | "next" => Ok(DebuggerCommand::StepOver()),
+      "o" | "out" | "finish" => Ok(DebuggerCommand::StepOut()),
+      "bt" | "backtrace" => Ok(DebuggerCommand::Backtrace()),
+      "v" | "vars" => Ok(DebuggerCommand::Vars()),
+      "p" | "print" => match argument {
+        Some(name) => Ok(DebuggerCommand::Print(name.to_owned())),
+        None => Err(String::from("Usage: print <name>"))
+      },
+      "l" | "list" => Ok(DebuggerCommand::List()),
+      "h" | "help" => Ok(DebuggerCommand::Help()),
+      "q" | "quit" => Ok(DebuggerCommand::Quit()),
+      _ => Err(format!("Unknown command '{}'. Type 'help' for a list of commands.", name))
+    }
+  }
+
+  pub fn help() -> &'static str {
+    "Commands:
+  b, break [file:]<line>   set a breakpoint, in the current file by default
+  d, delete [file:]<line>  remove a breakpoint
+  bl, breakpoints          list breakpoints
+  c, continue              run until the next breakpoint
+  s, step                  step into the next statement
+  n, next                  step over function calls
+  o, out, finish           run until the current function returns
+  bt, backtrace            print the call stack
+  v, vars                  dump the variables of every visible scope
+  p, print <name>          print a variable
+  l, list                  show the source around the current line
+  h, help                  show this help
+  q, quit                  stop the program"
+  }
+
+  fn location_argument(argument: Option<&str>) -> Result<(Option<String>, usize), String> {
+    let (file, line) = match argument.and_then(|a| a.rsplit_once(':')) {
+      Some((file, line)) => (Some(file.to_owned()), Some(line)),
+      None => (None, argument)
+    };
+
+    match line.map(|l| l.parse::<usize>()) {
+      Some(Ok(line)) if line > 0 => Ok((file, line)),
+      _ => Err(String::from("Expected a line number, optionally preceded by a file and ':'."))
+    }
+  }
+}
@@ -0,0 +1,198 @@
+pub mod debugger_command;
+pub mod stepper;
+
+use std::{cell::RefCell, collections::HashMap, fs, io::{self, BufRead, Write}, path::PathBuf, process, rc::Rc};
+
+use debugger_command::DebuggerCommand;
+use stepper::{Location, StepMode, Stepper};
+
+use crate::environment::Environment;
+use crate::module::ModuleLoader;
+use crate::runtime::call_stack::CallStack;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_hook::RuntimeHook;
+use crate::statement::Statement;
+
+// Interactive debugger driven by commands read from stdin. Output goes to stderr so the program's own output stays untouched.
+pub struct Debugger {
+  // Lines of every file shown so far, read the first time they are needed.
+  sources: HashMap<Rc<PathBuf>, Vec<String>>,
+  stepper: Stepper
+}
+
+impl Debugger {
+  pub fn new() -> Self {
+    Self {
+      sources: HashMap::new(),
+      stepper: Stepper::new(StepMode::StepIn)
+    }
+  }
+
+  fn prompt(&mut self, location: Location, env: &Rc<RefCell<Environment>>) {
+    let depth = CallStack::depth();
+    self.stepper.stopped(location.clone(), depth);
+    eprintln!("Stopped at {}: {}", Debugger::describe(&location), self.source_line(&location).trim());
+
+    let stdin = io::stdin();
+    loop {
+      eprint!("(debug) ");
+      io::stderr().flush().unwrap();
+
+      let mut input = String::new();
+      match stdin.lock().read_line(&mut input) {
+        Ok(0) | Err(_) => {
+          // Nobody is driving the session any more; let the program finish.
+          self.stepper.breakpoints.clear();
+          self.stepper.resume(StepMode::Continue);
+          return;
+        },
+        Ok(_) => {}
+      }
+
+      if input.trim().is_empty() {
+        continue;
+      }
+
+      match DebuggerCommand::parse(&input) {
+        Ok(command) => match command {
+          DebuggerCommand::Break(file, l) => match Debugger::breakpoint(file, l, &location) {
+            Ok(breakpoint) => {
+              eprintln!("Breakpoint set at {}", Debugger::describe(&breakpoint));
+              self.stepper.breakpoints.insert(breakpoint);
+            },
+            Err(msg) => eprintln!("{}", msg)
+          },
+          DebuggerCommand::Delete(file, l) => match Debugger::breakpoint(file, l, &location) {
+            Ok(breakpoint) if self.stepper.breakpoints.remove(&breakpoint) => eprintln!("Breakpoint removed from {}", Debugger::describe(&breakpoint)),
+            Ok(breakpoint) => eprintln!("No breakpoint at {}", Debugger::describe(&breakpoint)),
+            Err(msg) => eprintln!("{}", msg)
+          },
+          DebuggerCommand::Breakpoints() => {
+            if self.stepper.breakpoints.is_empty() {
+              eprintln!("No breakpoints.");
+            }
+            for breakpoint in self.stepper.breakpoints.clone() {
+              eprintln!("  {}: {}", Debugger::describe(&breakpoint), self.source_line(&breakpoint).trim());
+            }
+          },
+          DebuggerCommand::Continue() => {
+            self.stepper.resume(StepMode::Continue);
+            return;
+          },
+          DebuggerCommand::StepIn() => {
+            self.stepper.resume(StepMode::StepIn);
+            return;
+          },
+          DebuggerCommand::StepOver() => {
+            self.stepper.resume(StepMode::StepOver(depth));
+            return;
+          },
+          DebuggerCommand::StepOut() => {
+            self.stepper.resume(StepMode::StepOut(depth));
+            return;
+          },
+          DebuggerCommand::Backtrace() => Debugger::print_backtrace(location.1),
+          DebuggerCommand::Vars() => Debugger::print_scopes(env),
+          DebuggerCommand::Print(name) => match env.borrow().get(&name) {
+            Some(value) => eprintln!("{} = {}", name, value.to_string()),
+            None => eprintln!("{}", RuntimeError::UndefinedIdentifier(name).to_string())
+          },
+          DebuggerCommand::List() => self.print_source(&location),
+          DebuggerCommand::Help() => eprintln!("{}", DebuggerCommand::help()),
+          DebuggerCommand::Quit() => process::exit(0)
+        },
+        Err(msg) => eprintln!("{}", msg)
+      }
+    }
+  }
+
+  fn print_backtrace(line: usize) {
+    for frame in CallStack::backtrace(line) {
+      eprintln!("{}", frame);
+    }
+  }
+
+  fn print_scopes(env: &Rc<RefCell<Environment>>) {
+    let mut scope = Some(env.clone());
+    let mut i = 0;
+
+    while let Some(current) = scope {
+      let current = current.borrow();
+      let kind = if current.enclosing().is_none() { " (global)" } else { "" };
+      eprintln!("Scope {}{}:", i, kind);
+
+      for (name, value) in current.bindings() {
+        eprintln!("  {} = {}", name, value.to_string());
+      }
+
+      scope = current.enclosing();
+      i += 1;
+    }
+  }
+
+  fn print_source(&mut self, location: &Location) {
+    let (file, line) = location.clone();
+    let first = if line > 3 { line - 3 } else { 1 };
+    let last = (line + 3).min(self.source(&file).len());
+
+    for l in first..=last {
+      let here = (file.clone(), l);
+      let marker = if l == line { "->" } else if self.stepper.breakpoints.contains(&here) { " *" } else { "  " };
+      eprintln!("{} {:>4} | {}", marker, l, self.source_line(&here));
+    }
+  }
+
+  fn source_line(&mut self, location: &Location) -> String {
+    match self.source(&location.0).get(location.1 - 1) {
+      Some(l) => l.to_owned(),
+      None => String::new()
+    }
+  }
+
+  fn source(&mut self, file: &Option<Rc<PathBuf>>) -> &[String] {
+    match file {
+      Some(file) => self.sources.entry(file.clone())
+        .or_insert_with(|| fs::read_to_string(file.as_ref()).unwrap_or_default().lines().map(|l| l.to_owned()).collect()),
+      None => &[]
+    }
+  }
+
+  // `b 3` is in the file the program stopped in; `b lib.lox:3` names a file relative to the program.
+  fn breakpoint(file: Option<String>, line: usize, stopped_at: &Location) -> Result<Location, String> {
+    let file = match file {
+      Some(name) => {
+        let main = ModuleLoader::main_file();
+        let base = main.as_deref().and_then(|m| m.parent()).map(|d| d.join(&name)).unwrap_or(PathBuf::from(&name));
+        match fs::canonicalize(base) {
+          Ok(path) => Some(Rc::new(path)),
+          Err(e) => return Err(format!("Cannot set a breakpoint in {}: {}", name, e))
+        }
+      },
+      None => stopped_at.0.clone()
+    };
+
+    Ok((file, line))
+  }
+
+  // Lines of the program itself go without the file name.
+  fn describe(location: &Location) -> String {
+    match &location.0 {
+      Some(file) if !ModuleLoader::is_main_file(Some(file)) => format!("line {} of {}", location.1, ModuleLoader::display(file)),
+      _ => format!("line {}", location.1)
+    }
+  }
+}
+
+impl RuntimeHook for Debugger {
+  fn before_statement(&mut self, _statement: &Statement, line: usize, env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    let depth = CallStack::depth();
+    let location = (ModuleLoader::current_file(), line);
+
+    if self.stepper.should_stop(&location, depth).is_some() {
+      self.prompt(location.clone(), env);
+    }
+
+    self.stepper.visit(location, depth);
+    Ok(())
+  }
+}
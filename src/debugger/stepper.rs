@@ -0,0 +1,69 @@
+use std::{collections::BTreeSet, path::PathBuf, rc::Rc};
+
+// A line of a file, as `ModuleLoader::current_file` names it.
+pub type Location = (Option<Rc<PathBuf>>, usize);
+
+pub enum StepMode {
+  Continue,
+  StepIn,
+  StepOver(usize),
+  StepOut(usize)
+}
+
+pub enum StopReason {
+  Step,
+  Breakpoint
+}
+
+// Decides where execution should pause. Shared by the interactive debugger and the DAP server.
+pub struct Stepper {
+  pub breakpoints: BTreeSet<Location>,
+  mode: StepMode,
+  // Location and call depth of the previous statement and of the last stop.
+  last_location: Option<(Location, usize)>,
+  stop_location: Option<(Location, usize)>
+}
+
+impl Stepper {
+  pub fn new(mode: StepMode) -> Self {
+    Self { breakpoints: BTreeSet::new(), mode, last_location: None, stop_location: None }
+  }
+
+  pub fn resume(&mut self, mode: StepMode) {
+    self.mode = mode;
+  }
+
+  // Must be called for every statement, after any pause has ended.
+  pub fn visit(&mut self, location: Location, depth: usize) {
+    self.last_location = Some((location, depth));
+  }
+
+  pub fn stopped(&mut self, location: Location, depth: usize) {
+    self.stop_location = Some((location, depth));
+  }
+
+  pub fn should_stop(&self, location: &Location, depth: usize) -> Option<StopReason> {
+    let at = |visited: &Option<(Location, usize)>| matches!(visited, Some((l, d)) if l == location && *d == depth);
+    let moved = !at(&self.stop_location);
+
+    let step_done = match self.mode {
+      StepMode::StepIn => moved,
+      StepMode::StepOver(d) => moved && depth <= d,
+      StepMode::StepOut(d) => depth < d,
+      StepMode::Continue => false
+    };
+
+    if step_done {
+      return Some(StopReason::Step);
+    }
+
+    // A breakpoint fires once per visit of its line, not for every statement on it.
+    let entered_line = !at(&self.last_location);
+
+    if entered_line && self.breakpoints.contains(location) {
+      return Some(StopReason::Breakpoint);
+    }
+
+    None
+  }
+}
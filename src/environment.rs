@@ -1,48 +1,179 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use crate::runtime::{runtime_error::RuntimeError, runtime_type::RuntimeType};
+use crate::token::Token;
 
+// The storage of one variable. Closures share it with the scope that declared the variable.
+pub type Binding = Rc<RefCell<Rc<RuntimeType>>>;
+
+// Variables live in slots numbered in declaration order, which is how the resolver refers to locals. Names are kept
+// next to them for code that was not resolved (globals, the debugger); only the global scope indexes them in a map.
 pub struct Environment{
   enclosing: Option<Rc<RefCell<Environment>>>,
-  map: Box<HashMap<String, Rc<RuntimeType>>>
+  slots: Vec<Binding>,
+  names: Vec<Rc<Token>>,
+  globals: Option<HashMap<String, usize>>
 }
 
 impl Environment {
   pub fn global() -> Self {
-    Self { enclosing: None, map: Box::new(HashMap::new())}
+    Self { enclosing: None, slots: Vec::new(), names: Vec::new(), globals: Some(HashMap::new()) }
   }
 
   pub fn local(enclosing: Rc<RefCell<Environment>>) -> Self {
-    Self { enclosing: Some(enclosing), map: Box::new(HashMap::new())}
+    Self { enclosing: Some(enclosing), slots: Vec::new(), names: Vec::new(), globals: None }
   }
 
   // Example usage: var a = 2;
-  pub fn define(&mut self, key: String, value: Rc<RuntimeType>) {
-    self.map.insert(key, value);
+  // Declaring a name again in the same scope assigns the variable in its slot, which closures see.
+  pub fn define(&mut self, name: &Rc<Token>, value: Rc<RuntimeType>) {
+    if let Some(slot) = self.slot_of(&name.lexeme) {
+      *self.slots[slot].borrow_mut() = value;
+      return;
+    }
+
+    self.bind(name, Rc::new(RefCell::new(value)));
+  }
+
+  // Adds a variable whose storage is shared with another scope, like the ones a closure captured.
+  pub fn bind(&mut self, name: &Rc<Token>, binding: Binding) {
+    if let Some(slot) = self.slot_of(&name.lexeme) {
+      self.slots[slot] = binding;
+      return;
+    }
+
+    if let Some(globals) = &mut self.globals {
+      globals.insert(name.lexeme.to_string(), self.slots.len());
+    }
+
+    self.slots.push(binding);
+    self.names.push(name.clone());
+  }
+
+  // For natives and other bindings that don't come from source code.
+  pub fn define_builtin(&mut self, name: &str, value: Rc<RuntimeType>) {
+    self.define(&Rc::new(Token::t_identifier(name.to_owned(), 0)), value);
   }
 
   // Example usage: a = 2;
-  pub fn assign(&mut self, key: String, value: Rc<RuntimeType>) -> Result<(), RuntimeError> {
-    if self.map.contains_key(&key) {
-      self.define(key, value);
+  pub fn assign(&mut self, name: &str, value: Rc<RuntimeType>) -> Result<(), RuntimeError> {
+    if let Some(slot) = self.slot_of(name) {
+      *self.slots[slot].borrow_mut() = value;
       return Ok(());
     }
 
     match &self.enclosing {
-      Some(enclosing) => enclosing.borrow_mut().assign(key, value),
-      None => Err(RuntimeError::UndefinedIdentifier(key))
+      Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+      None => Err(RuntimeError::UndefinedIdentifier(name.to_owned()))
     }
   }
 
   // Example usage: print a;
-  pub fn get(&self, key: &String) -> Option<Rc<RuntimeType>> {
-    match self.map.get(key) {
-      Some(v) => Some(v.clone()),
+  pub fn get(&self, name: &str) -> Option<Rc<RuntimeType>> {
+    match self.slot_of(name) {
+      Some(slot) => Some(self.slots[slot].borrow().clone()),
       None => match &self.enclosing {
-        Some(enclosing) => enclosing.borrow_mut().get(key),
+        Some(enclosing) => enclosing.borrow().get(name),
         None => None
       }
     }
   }
-}
 
+  // Only looks at this scope, not the enclosing ones.
+  pub fn get_local(&self, name: &str) -> Option<Rc<RuntimeType>> {
+    self.slot_of(name).map(|slot| self.slots[slot].borrow().clone())
+  }
+
+  // A variable the resolver found `depth` scopes up.
+  pub fn get_at(&self, depth: usize, slot: usize) -> Option<Rc<RuntimeType>> {
+    self.binding_at(depth, slot).map(|binding| binding.borrow().clone())
+  }
+
+  // The storage of a variable the resolver found `depth` scopes up, for a closure to capture.
+  pub fn binding_at(&self, depth: usize, slot: usize) -> Option<Binding> {
+    if depth == 0 {
+      return self.slots.get(slot).cloned();
+    }
+
+    self.enclosing.as_ref().and_then(|enclosing| enclosing.borrow().binding_at(depth - 1, slot))
+  }
+
+  pub fn assign_at(&mut self, depth: usize, slot: usize, value: Rc<RuntimeType>) -> bool {
+    if depth == 0 {
+      return match self.slots.get(slot) {
+        Some(binding) => {
+          *binding.borrow_mut() = value;
+          true
+        },
+        None => false
+      };
+    }
+
+    match &self.enclosing {
+      Some(enclosing) => enclosing.borrow_mut().assign_at(depth - 1, slot, value),
+      None => false
+    }
+  }
+
+  fn slot_of(&self, name: &str) -> Option<usize> {
+    match &self.globals {
+      Some(globals) => globals.get(name).copied(),
+      None => self.names.iter().position(|n| n.lexeme == name)
+    }
+  }
+
+  // A sibling scope whose variables start with the current values but live in new bindings. Each loop iteration runs
+  // in one, so closures created in different iterations don't share the loop variable.
+  pub fn copy(&self) -> Self {
+    let slots = self.slots.iter()
+      .map(|binding| Rc::new(RefCell::new(binding.borrow().clone())))
+      .collect();
+
+    Self { enclosing: self.enclosing.clone(), slots, names: self.names.clone(), globals: self.globals.clone() }
+  }
+
+  // The scope `depth` levels up from `env`, or the outermost one if the chain is shorter.
+  pub fn ancestor(env: &Rc<RefCell<Environment>>, depth: usize) -> Rc<RefCell<Environment>> {
+    let mut scope = env.clone();
+
+    for _ in 0..depth {
+      let enclosing = match scope.borrow().enclosing() {
+        Some(enclosing) => enclosing,
+        None => break
+      };
+      scope = enclosing;
+    }
+
+    scope
+  }
+
+  // Used by the garbage collector to break a cycle of scopes nothing else can reach.
+  pub fn clear(&mut self) {
+    self.slots.clear();
+    self.names.clear();
+    if let Some(globals) = &mut self.globals {
+      globals.clear();
+    }
+    self.enclosing = None;
+  }
+
+  // Used by the debugger to walk the scope chain.
+  pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+    self.enclosing.clone()
+  }
+
+  // Bindings of this scope only, sorted by name.
+  pub fn bindings(&self) -> Vec<(String, Rc<RuntimeType>)> {
+    let mut bindings: Vec<(String, Rc<RuntimeType>)> = self.names.iter().zip(self.slots.iter())
+      .map(|(name, binding)| (name.lexeme.to_string(), binding.borrow().clone()))
+      .collect();
+
+    bindings.sort_by(|a, b| a.0.cmp(&b.0));
+    bindings
+  }
+
+  // Used by the garbage collector, which traces the bindings themselves since closures share them.
+  pub fn slots(&self) -> Vec<Binding> {
+    self.slots.clone()
+  }
+}
@@ -2,9 +2,14 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::environment::Environment;
+use crate::runtime::garbage_collector::GarbageCollector;
 use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_function::RuntimeFunction;
+use crate::runtime::runtime_hook::RuntimeHooks;
+use crate::runtime::runtime_bigint::{RuntimeBigInt, MAX_POW_LIMBS};
 use crate::runtime::runtime_type::RuntimeType;
-use crate::{expression::Expression, token::TokenType};
+use crate::sandbox::Sandbox;
+use crate::{expression::{Expression, Resolved}, token::{Token, TokenType}};
 
 pub struct ExprEvaluator;
 
@@ -12,10 +17,10 @@ impl ExprEvaluator {
   pub fn evaluate(expression: &Expression, env: &Rc<RefCell<Environment>>) -> Result<RuntimeType, RuntimeError> {
     match expression {
       Expression::Nil() => Ok(RuntimeType::Nil()),
-      Expression::Assign(token, expression) => {
+      Expression::Assign(token, expression, resolved) => {
         match ExprEvaluator::evaluate(expression, env) {
           Ok(value) => {
-            match env.borrow_mut().assign(token.lexeme.to_string(), Rc::new(value.clone())) {
+            match ExprEvaluator::assign_variable(token, resolved, Rc::new(value.clone()), env) {
               Ok(()) => Ok(value),
               Err(e) => Err(e)
             }
@@ -23,21 +28,25 @@ impl ExprEvaluator {
           Err(e) => Err(e)
         }
       },
-      Expression::Identifier(token) => {
-        match env.borrow().get(&token.lexeme.to_string()) {
-          Some(v) => Ok((*v).clone()),
-          None => Err(RuntimeError::UndefinedIdentifier(token.lexeme.to_string()))
-        }
-      },
-      Expression::Literal(token) => {
-        match token.token_type {
-          TokenType::Nil => Ok(RuntimeType::Nil()),
-          TokenType::True | TokenType::False => Ok(RuntimeType::Boolean(token.lexeme.parse::<bool>().unwrap())),
-          TokenType::Number => Ok(RuntimeType::Number(token.literal.parse::<f64>().unwrap())),
-          TokenType::String => Ok(RuntimeType::String(token.literal.to_string())),
-          _ => Ok(RuntimeType::Nil())
-        }
+      Expression::Update(token, operator, postfix, resolved) => {
+        let old = ExprEvaluator::variable(token, resolved, env)?;
+
+        let step = if operator.token_type == TokenType::PlusPlus { 1 } else { -1 };
+        let new = match old {
+          RuntimeType::Integer(i) => match i.checked_add(step) {
+            Some(n) => RuntimeType::Integer(n),
+            None => return Err(RuntimeError::IntegerOverflow(operator.line))
+          },
+          RuntimeType::Number(n) => RuntimeType::Number(n + step as f64),
+          RuntimeType::BigInt(ref i) => RuntimeType::BigInt(Rc::new(i.add(&RuntimeBigInt::from_i64(step)))),
+          _ => return Err(RuntimeError::UnaryError(format!("Operand must be a number.\n[line {}]", operator.line)))
+        };
+        ExprEvaluator::assign_variable(token, resolved, Rc::new(new.clone()), env)?;
+
+        Ok(if *postfix { old } else { new })
       },
+      Expression::Identifier(token, resolved) => ExprEvaluator::variable(token, resolved, env),
+      Expression::Literal(_token, value) => Ok(value.clone()),
       Expression::Grouping(e) => ExprEvaluator::evaluate(e, env),
       Expression::Unary(token, e) => {
         let value = ExprEvaluator::evaluate(e, env);
@@ -48,6 +57,11 @@ impl ExprEvaluator {
               TokenType::Bang => Ok(RuntimeType::Boolean(!v.is_truthy())),
               TokenType::Minus => match v {
                 RuntimeType::Number(n) => Ok(RuntimeType::Number(-n)),
+                RuntimeType::BigInt(i) => Ok(RuntimeType::BigInt(Rc::new(i.negate()))),
+                RuntimeType::Integer(i) => match i.checked_neg() {
+                  Some(n) => Ok(RuntimeType::Integer(n)),
+                  None => Err(RuntimeError::IntegerOverflow(token.line))
+                },
                 _ => Err(RuntimeError::UnaryError(format!("Operand must be a number.\n[line {}]", token.line)))
               },
               _ => Ok(RuntimeType::Nil())
@@ -65,21 +79,23 @@ impl ExprEvaluator {
 
             match right_value_r {
               Ok(right_value) => {
+                if let (RuntimeType::Integer(l), RuntimeType::Integer(r)) = (&left_value, &right_value) {
+                  return ExprEvaluator::integer_binary(*l, token, *r);
+                }
+                if let Some((l, r)) = ExprEvaluator::bigint_operands(&left_value, &right_value) {
+                  return ExprEvaluator::bigint_binary(&l, token, &r);
+                }
+                if let (Some(l), Some(r)) = (left_value.as_number(), right_value.as_number()) {
+                  // An integer and a number are equal only if the number is exactly that integer.
+                  return match token.token_type {
+                    TokenType::EqualEqual => Ok(RuntimeType::Boolean(left_value.equals(&right_value))),
+                    TokenType::BangEqual => Ok(RuntimeType::Boolean(!left_value.equals(&right_value))),
+                    _ => ExprEvaluator::number_binary(l, token, r)
+                  };
+                }
+
                 match left_value {
-                  RuntimeType::Number(ln) => match right_value {
-                    RuntimeType::Number(rn) => match token.token_type {
-                      TokenType::Plus => Ok(RuntimeType::Number(ln + rn)),
-                      TokenType::Minus => Ok(RuntimeType::Number(ln - rn)),
-                      TokenType::Star => Ok(RuntimeType::Number(ln * rn)),
-                      TokenType::Slash => Ok(RuntimeType::Number(ln / rn)),
-                      TokenType::Greater => Ok(RuntimeType::Boolean(ln > rn)),
-                      TokenType::GreaterEqual => Ok(RuntimeType::Boolean(ln >= rn)),
-                      TokenType::Less => Ok(RuntimeType::Boolean(ln < rn)),
-                      TokenType::LessEqual => Ok(RuntimeType::Boolean(ln <= rn)),
-                      TokenType::EqualEqual => Ok(RuntimeType::Boolean(ln == rn)),
-                      TokenType::BangEqual => Ok(RuntimeType::Boolean(ln != rn)),
-                      _ => Ok(RuntimeType::Nil())
-                    },
+                  RuntimeType::Number(_) | RuntimeType::Integer(_) => match right_value {
                     RuntimeType::String(_rs) => match token.token_type {
                       TokenType::EqualEqual => Ok(RuntimeType::Boolean(false)),
                       TokenType::BangEqual => Ok(RuntimeType::Boolean(true)),
@@ -89,12 +105,15 @@ impl ExprEvaluator {
                   },
                   RuntimeType::String(ls) => match right_value {
                     RuntimeType::String(rs) => match token.token_type {
-                      TokenType::Plus => Ok(RuntimeType::String(ls + &rs)),
+                      TokenType::Plus => {
+                        Sandbox::check_string(ls.chars().count() + rs.chars().count())?;
+                        Ok(RuntimeType::String(ls + &rs))
+                      },
                       TokenType::EqualEqual => Ok(RuntimeType::Boolean(ls == rs)),
                       TokenType::BangEqual => Ok(RuntimeType::Boolean(ls != rs)),
                       _ => Err(RuntimeError::BinaryError(format!("Operands must be numbers.\n[line {}]", token.line)))
                     },
-                    RuntimeType::Number(_rn) => match token.token_type {
+                    RuntimeType::Number(_) | RuntimeType::Integer(_) => match token.token_type {
                       TokenType::EqualEqual => Ok(RuntimeType::Boolean(false)),
                       TokenType::BangEqual => Ok(RuntimeType::Boolean(true)),
                       _ => Err(RuntimeError::BinaryError(format!("Operands must be numbers.\n[line {}]", token.line)))
@@ -121,10 +140,11 @@ impl ExprEvaluator {
       Expression::Logical(left, operator, right) => {
         match ExprEvaluator::evaluate(left, env) {
           Ok(l) => {
-            if operator.token_type == TokenType::Or {
-              if l.is_truthy() { return Ok(l); }
-            } else {
-              if !l.is_truthy() { return Ok(l); }
+            let short_circuit = ExprEvaluator::short_circuits(operator, &l);
+            RuntimeHooks::on_branch(Some(operator), if short_circuit { 0 } else { 1 });
+
+            if short_circuit {
+              return Ok(l);
             }
 
             match ExprEvaluator::evaluate(right, env) {
@@ -135,25 +155,186 @@ impl ExprEvaluator {
           Err(e) => Err(e)
         }
       },
-      Expression::Call(callee, arguments) => {
-        match ExprEvaluator::evaluate(callee, env) {
-          Ok(eval_callee) => {
-            let mut eval_args: Vec<Rc<RuntimeType>> = Vec::new();
-            for arg in arguments {
-              match ExprEvaluator::evaluate(arg, env) {
-                Ok(v) => eval_args.push(Rc::new(v)),
-                Err(e) => return Err(e)
-              }
-            }
+      Expression::Conditional(condition, question, then_expr, else_expr) => {
+        let condition = ExprEvaluator::evaluate(condition, env)?;
+        RuntimeHooks::on_branch(Some(question), if condition.is_truthy() { 0 } else { 1 });
 
-            match eval_callee {
-              RuntimeType::Function(function) => function.call(eval_args),
-              _ => Err(RuntimeError::UndefinedIdentifier(callee.to_string()))
-            }
+        ExprEvaluator::evaluate(if condition.is_truthy() { then_expr } else { else_expr }, env)
+      },
+      Expression::Call(callee, arguments) => {
+        match ExprEvaluator::evaluate_call(callee, arguments, env) {
+          Ok((function, eval_args)) => function.call(eval_args, callee.line().unwrap_or(0), env),
+          Err(e) => Err(e)
+        }
+      },
+      Expression::Interpolation(_token, parts) => {
+        let mut result = String::new();
+        for part in parts {
+          result.push_str(&ExprEvaluator::evaluate(part, env)?.to_string());
+        }
+        Sandbox::check_string(result.chars().count())?;
+        Ok(RuntimeType::String(result))
+      },
+      Expression::Get(object, name) => {
+        match ExprEvaluator::evaluate(object, env) {
+          Ok(RuntimeType::Module(module)) => match module.get_export(&name.lexeme) {
+            Some(value) => Ok((*value).clone()),
+            None => Err(RuntimeError::UndefinedIdentifier(format!("{}.{}", object.to_string(), name.lexeme)))
           },
+          Ok(_) => Err(RuntimeError::PropertyError(format!("Only modules have properties.\n[line {}]", name.line))),
           Err(e) => Err(e)
         }
       }
     }
   }
-}
\ No newline at end of file
+
+  // A resolved variable is read from its slot. Anything else, or a slot the scope doesn't have, is looked up by name.
+  fn variable(token: &Rc<Token>, resolved: &Resolved, env: &Rc<RefCell<Environment>>) -> Result<RuntimeType, RuntimeError> {
+    let value = match resolved {
+      Some((depth, slot)) => env.borrow().get_at(*depth, *slot).or_else(|| env.borrow().get(&token.lexeme)),
+      None => env.borrow().get(&token.lexeme)
+    };
+
+    match value {
+      Some(v) => Ok((*v).clone()),
+      None => Err(RuntimeError::UndefinedIdentifier(token.lexeme.to_string()))
+    }
+  }
+
+  fn assign_variable(token: &Rc<Token>, resolved: &Resolved, value: Rc<RuntimeType>, env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    if let Some((depth, slot)) = resolved {
+      if env.borrow_mut().assign_at(*depth, *slot, value.clone()) {
+        return Ok(());
+      }
+    }
+
+    env.borrow_mut().assign(&token.lexeme, value)
+  }
+
+  fn number_binary(ln: f64, token: &Rc<Token>, rn: f64) -> Result<RuntimeType, RuntimeError> {
+    match token.token_type {
+      TokenType::Slash | TokenType::Percent | TokenType::TildeSlash if rn == 0. => {
+        Err(RuntimeError::BinaryError(format!("Division by zero.\n[line {}]", token.line)))
+      },
+      TokenType::Plus => Ok(RuntimeType::Number(ln + rn)),
+      TokenType::Minus => Ok(RuntimeType::Number(ln - rn)),
+      TokenType::Star => Ok(RuntimeType::Number(ln * rn)),
+      TokenType::Slash => Ok(RuntimeType::Number(ln / rn)),
+      // Floored, so `a == (a ~/ b) * b + a % b` and the result has the sign of the divisor.
+      TokenType::Percent => Ok(RuntimeType::Number(ln - rn * (ln / rn).floor())),
+      TokenType::TildeSlash => Ok(RuntimeType::Number((ln / rn).floor())),
+      TokenType::StarStar => Ok(RuntimeType::Number(ln.powf(rn))),
+      TokenType::Greater => Ok(RuntimeType::Boolean(ln > rn)),
+      TokenType::GreaterEqual => Ok(RuntimeType::Boolean(ln >= rn)),
+      TokenType::Less => Ok(RuntimeType::Boolean(ln < rn)),
+      TokenType::LessEqual => Ok(RuntimeType::Boolean(ln <= rn)),
+      TokenType::EqualEqual => Ok(RuntimeType::Boolean(ln == rn)),
+      TokenType::BangEqual => Ok(RuntimeType::Boolean(ln != rn)),
+      _ => Ok(RuntimeType::Nil())
+    }
+  }
+
+  // Same rules as for numbers, but exact. `/` and negative powers still give a number.
+  fn integer_binary(l: i64, token: &Rc<Token>, r: i64) -> Result<RuntimeType, RuntimeError> {
+    let integer = |result: Option<i64>| match result {
+      Some(i) => Ok(RuntimeType::Integer(i)),
+      None => Err(RuntimeError::IntegerOverflow(token.line))
+    };
+
+    match token.token_type {
+      TokenType::Slash | TokenType::Percent | TokenType::TildeSlash if r == 0 => {
+        Err(RuntimeError::BinaryError(format!("Division by zero.\n[line {}]", token.line)))
+      },
+      TokenType::Plus => integer(l.checked_add(r)),
+      TokenType::Minus => integer(l.checked_sub(r)),
+      TokenType::Star => integer(l.checked_mul(r)),
+      TokenType::Slash => Ok(RuntimeType::Number(l as f64 / r as f64)),
+      TokenType::Percent => integer(l.checked_rem(r).map(|m| if m != 0 && (m < 0) != (r < 0) { m + r } else { m })),
+      TokenType::TildeSlash => integer(l.checked_div(r).map(|q| if l % r != 0 && (l < 0) != (r < 0) { q - 1 } else { q })),
+      TokenType::StarStar if r < 0 => Ok(RuntimeType::Number((l as f64).powf(r as f64))),
+      TokenType::StarStar => integer(u32::try_from(r).ok().and_then(|r| l.checked_pow(r))),
+      TokenType::Greater => Ok(RuntimeType::Boolean(l > r)),
+      TokenType::GreaterEqual => Ok(RuntimeType::Boolean(l >= r)),
+      TokenType::Less => Ok(RuntimeType::Boolean(l < r)),
+      TokenType::LessEqual => Ok(RuntimeType::Boolean(l <= r)),
+      TokenType::EqualEqual => Ok(RuntimeType::Boolean(l == r)),
+      TokenType::BangEqual => Ok(RuntimeType::Boolean(l != r)),
+      _ => Ok(RuntimeType::Nil())
+    }
+  }
+
+  // Whether a logical operator returns its left operand without evaluating the right one.
+  pub fn short_circuits(operator: &Token, left: &RuntimeType) -> bool {
+    match operator.token_type {
+      TokenType::Or => left.is_truthy(),
+      TokenType::QuestionQuestion => !matches!(left, RuntimeType::Nil()),
+      _ => !left.is_truthy()
+    }
+  }
+
+  // The function being called and its arguments, evaluated left to right.
+  pub fn evaluate_call(callee: &Expression, arguments: &[Expression], env: &Rc<RefCell<Environment>>) -> Result<(Rc<RuntimeFunction>, Vec<Rc<RuntimeType>>), RuntimeError> {
+    let eval_callee = ExprEvaluator::evaluate(callee, env)?;
+    // Evaluating an argument can run a collection, which must not free the callee or the arguments before it.
+    let mut roots = vec![GarbageCollector::root_value(&eval_callee)];
+
+    let mut eval_args: Vec<Rc<RuntimeType>> = Vec::new();
+    for arg in arguments {
+      let value = ExprEvaluator::evaluate(arg, env)?;
+      roots.push(GarbageCollector::root_value(&value));
+      eval_args.push(Rc::new(value));
+    }
+
+    match eval_callee {
+      RuntimeType::Function(function) => Ok((function, eval_args)),
+      _ => Err(RuntimeError::UndefinedIdentifier(callee.to_string()))
+    }
+  }
+
+  // Two bigints, or a bigint and an integer, which is promoted.
+  fn bigint_operands(left: &RuntimeType, right: &RuntimeType) -> Option<(RuntimeBigInt, RuntimeBigInt)> {
+    match (left, right) {
+      (RuntimeType::BigInt(l), RuntimeType::BigInt(r)) => Some(((**l).clone(), (**r).clone())),
+      (RuntimeType::BigInt(l), RuntimeType::Integer(r)) => Some(((**l).clone(), RuntimeBigInt::from_i64(*r))),
+      (RuntimeType::Integer(l), RuntimeType::BigInt(r)) => Some((RuntimeBigInt::from_i64(*l), (**r).clone())),
+      _ => None
+    }
+  }
+
+  // Bigints have no fractions, so `/` rounds down like `~/`.
+  fn bigint_binary(l: &RuntimeBigInt, token: &Rc<Token>, r: &RuntimeBigInt) -> Result<RuntimeType, RuntimeError> {
+    let bigint = |value: RuntimeBigInt| -> Result<RuntimeType, RuntimeError> {
+      Sandbox::check_bigint(value.bits())?;
+      Sandbox::allocate(1)?;
+      Ok(RuntimeType::BigInt(Rc::new(value)))
+    };
+
+    match token.token_type {
+      TokenType::Slash | TokenType::Percent | TokenType::TildeSlash => match l.div_rem(r) {
+        Some((quotient, remainder)) => bigint(if token.token_type == TokenType::Percent { remainder } else { quotient }),
+        None => Err(RuntimeError::BinaryError(format!("Division by zero.\n[line {}]", token.line)))
+      },
+      TokenType::Plus => bigint(l.add(r)),
+      TokenType::Minus => bigint(l.sub(r)),
+      TokenType::Star => {
+        Sandbox::check_bigint(l.bits() + r.bits())?;
+        bigint(l.mul(r)?)
+      },
+      TokenType::StarStar => match r.to_i64() {
+        Some(exponent) if exponent < 0 => Ok(RuntimeType::Number(l.to_f64().powf(exponent as f64))),
+        Some(exponent) if l.pow_bits(exponent as u64) <= (MAX_POW_LIMBS * 32) as f64 => {
+          Sandbox::check_bigint(l.pow_bits(exponent as u64) as u64)?;
+          bigint(l.pow(exponent as u64)?)
+        },
+        _ => Err(RuntimeError::BinaryError(format!("Exponent too large.\n[line {}]", token.line)))
+      },
+      TokenType::Greater => Ok(RuntimeType::Boolean(l > r)),
+      TokenType::GreaterEqual => Ok(RuntimeType::Boolean(l >= r)),
+      TokenType::Less => Ok(RuntimeType::Boolean(l < r)),
+      TokenType::LessEqual => Ok(RuntimeType::Boolean(l <= r)),
+      TokenType::EqualEqual => Ok(RuntimeType::Boolean(l == r)),
+      TokenType::BangEqual => Ok(RuntimeType::Boolean(l != r)),
+      _ => Ok(RuntimeType::Nil())
+    }
+  }
+}
@@ -1,26 +1,46 @@
+#![allow(
+  clippy::borrowed_box,
+  clippy::inherent_to_string,
+  clippy::ptr_arg,
+  clippy::useless_format
+)]
+
 pub mod parser;
 pub mod evaluator;
 
 use std::{borrow::Cow, rc::Rc};
+use crate::runtime::runtime_type::RuntimeType;
 use crate::token::{Token, TokenType};
 
+// Where the resolver found a local variable: how many scopes up, and its slot there. None for globals and for code that
+// wasn't resolved, which look the name up instead.
+pub type Resolved = Option<(usize, usize)>;
+
 #[derive(Clone)]
 pub enum Expression {
-  Literal(Rc<Token>),
+  // The token as written and the value it was parsed to.
+  Literal(Rc<Token>, RuntimeType),
   Unary(Rc<Token>, Box<Expression>),
   Binary(Box<Expression>, Rc<Token>, Box<Expression>),
   Grouping(Box<Expression>),
-  Identifier(Rc<Token>),
-  Assign(Rc<Token>, Box<Expression>),
+  Identifier(Rc<Token>, Resolved),
+  Assign(Rc<Token>, Box<Expression>, Resolved),
+  // `++a` / `a--`: the variable, the operator, and whether it is postfix (evaluating to the old value).
+  Update(Rc<Token>, Rc<Token>, bool, Resolved),
   Logical(Box<Expression>, Rc<Token>, Box<Expression>),
+  // cond ? then : else
+  Conditional(Box<Expression>, Rc<Token>, Box<Expression>, Box<Expression>),
   Call(Box<Expression>, Vec<Expression>),
+  Get(Box<Expression>, Rc<Token>),
+  // "a ${b} c": the parts are concatenated as strings.
+  Interpolation(Rc<Token>, Vec<Expression>),
   Nil(),
 }
 
 impl Expression {
   pub fn to_string(&self) -> String {
     match self {
-      Expression::Literal(token) => {
+      Expression::Literal(token, _value) => {
         match token.token_type {
           TokenType::Nil | TokenType::False | TokenType::True => token.lexeme.to_string(),
           _ => token.literal.to_string()
@@ -29,14 +49,41 @@ impl Expression {
       Expression::Unary(token, right) => Expression::parenthesize(&token.lexeme, &[right]),
       Expression::Binary(left, token, right) => Expression::parenthesize(&token.lexeme, &[left, right]),
       Expression::Grouping(expr) => Expression::parenthesize(&Cow::Borrowed("group"), &[expr]),
-      Expression::Identifier(token) => token.lexeme.to_string(),
-      Expression::Assign(token, expression) => format!("{} = {}", token.lexeme, expression.to_string()),
+      Expression::Identifier(token, _resolved) => token.lexeme.to_string(),
+      Expression::Assign(token, expression, _resolved) => format!("{} = {}", token.lexeme, expression.to_string()),
+      Expression::Update(token, operator, postfix, _resolved) => if *postfix { format!("{}{}", token.lexeme, operator.lexeme) } else { format!("{}{}", operator.lexeme, token.lexeme) },
       Expression::Logical(left, operator, right) => format!("{} {} {}", left.to_string(), operator.to_str(), right.to_string()),
+      Expression::Conditional(condition, _question, then_expr, else_expr) => format!("(? {} {} {})", condition.to_string(), then_expr.to_string(), else_expr.to_string()),
       Expression::Call(calle, arguments) => format!("{}{}", calle.to_string(), arguments.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ")),
+      Expression::Get(object, name) => format!("{}.{}", object.to_string(), name.lexeme),
+      Expression::Interpolation(_token, parts) => format!("(interpolate {})", parts.iter().map(|p| p.to_string()).collect::<Vec<String>>().join(" ")),
       Expression::Nil() => format!("nil")
     }
   }
 
+  // Leftmost token of the expression.
+  pub fn token(&self) -> Option<&Rc<Token>> {
+    match self {
+      Expression::Literal(token, _value) => Some(token),
+      Expression::Unary(token, _right) => Some(token),
+      Expression::Binary(left, token, _right) => left.token().or(Some(token)),
+      Expression::Grouping(expr) => expr.token(),
+      Expression::Identifier(token, _resolved) => Some(token),
+      Expression::Assign(token, _expression, _resolved) => Some(token),
+      Expression::Update(token, _operator, _postfix, _resolved) => Some(token),
+      Expression::Logical(left, operator, _right) => left.token().or(Some(operator)),
+      Expression::Conditional(condition, question, _then_expr, _else_expr) => condition.token().or(Some(question)),
+      Expression::Call(callee, _arguments) => callee.token(),
+      Expression::Get(object, name) => object.token().or(Some(name)),
+      Expression::Interpolation(token, _parts) => Some(token),
+      Expression::Nil() => None
+    }
+  }
+
+  pub fn line(&self) -> Option<usize> {
+    self.token().map(|token| token.line)
+  }
+
   fn parenthesize(name: &Cow<'static, str>, expressions: &[&Box<Expression>]) -> String {
     let mut expr_str = format!("({}", name);
 
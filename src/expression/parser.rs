@@ -1,8 +1,17 @@
+#![allow(
+  clippy::needless_return,
+  clippy::question_mark,
+  clippy::while_let_loop
+)]
+
 use std::rc::Rc;
 
 use crate::parser::parser_error::ParserError;
+use crate::runtime::runtime_bigint::RuntimeBigInt;
+use crate::runtime::runtime_number::RuntimeNumber;
+use crate::runtime::runtime_type::RuntimeType;
 use crate::parser::parser_utils::ParserUtils;
-use crate::token::{Token, TokenType};
+use crate::token::{StringPart, Token, TokenType};
 use crate::expression::Expression;
 
 pub struct ExprParser;
@@ -17,25 +26,80 @@ impl ExprParser {
   }
 
   pub fn assignment(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
-    match ExprParser::or(tokens, index) {
+    match ExprParser::conditional(tokens, index) {
       Ok(expr) => {
         match ParserUtils::match_advance(tokens, index, &[TokenType::Equal]) {
           Some(_equal) => {
             match ExprParser::assignment(tokens, index) {
               Ok(value) => match expr {
-                Expression::Identifier(token) => return Ok(Expression::Assign(token, Box::new(value))),
+                Expression::Identifier(token, _resolved) => Ok(Expression::Assign(token, Box::new(value), None)),
                 _ => Err(ParserError::InvalidAssignment(expr.to_string())),
               },
               Err(_e) => Err(ParserError::InvalidAssignment(expr.to_string()))
             }
           },
-          None => Ok(expr)
+          None => ExprParser::compound_assignment(tokens, index, expr)
         }
       },
       Err(e) => Err(e)
     }
   }
 
+  // `a += b` is parsed as `a = a + b`.
+  fn compound_assignment(tokens: &Vec<Rc<Token>>, index: &mut usize, expr: Expression) -> Result<Expression, ParserError> {
+    let operator = match ParserUtils::match_advance(tokens, index, &[TokenType::PlusEqual, TokenType::MinusEqual, TokenType::StarEqual, TokenType::SlashEqual]) {
+      Some(operator) => operator,
+      None => return Ok(expr)
+    };
+
+    let target = match &expr {
+      Expression::Identifier(token, _resolved) => token.clone(),
+      _ => return Err(ParserError::InvalidAssignment(expr.to_string()))
+    };
+
+    let binary_operator = match operator.token_type {
+      TokenType::PlusEqual => Token::t_plus(operator.line),
+      TokenType::MinusEqual => Token::t_minus(operator.line),
+      TokenType::StarEqual => Token::t_star(operator.line),
+      _ => Token::t_slash(operator.line)
+    };
+
+    match ExprParser::assignment(tokens, index) {
+      Ok(value) => Ok(Expression::Assign(target, Box::new(Expression::Binary(Box::new(expr), Rc::new(binary_operator), Box::new(value))), None)),
+      Err(_e) => Err(ParserError::InvalidAssignment(expr.to_string()))
+    }
+  }
+
+  // `cond ? a : b`, right-associative so `a ? b : c ? d : e` chains.
+  fn conditional(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
+    let condition = ExprParser::coalesce(tokens, index)?;
+
+    let question = match ParserUtils::match_advance(tokens, index, &[TokenType::Question]) {
+      Some(question) => question,
+      None => return Ok(condition)
+    };
+
+    let then_expr = ExprParser::expression(tokens, index)?;
+    if ParserUtils::match_advance(tokens, index, &[TokenType::Colon]).is_none() {
+      return Err(ParserError::MissingToken(TokenType::Colon));
+    }
+    let else_expr = ExprParser::conditional(tokens, index)?;
+
+    Ok(Expression::Conditional(Box::new(condition), question, Box::new(then_expr), Box::new(else_expr)))
+  }
+
+  // `a ?? b` is `a` unless it is nil. Evaluated like `or`.
+  fn coalesce(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
+    let mut expression = ExprParser::or(tokens, index)?;
+
+    while let Some(operator) = ParserUtils::match_advance(tokens, index, &[TokenType::QuestionQuestion]) {
+      let right = ExprParser::or(tokens, index)?;
+      expression = Expression::Logical(Box::new(expression), operator, Box::new(right));
+    }
+
+    Ok(expression)
+  }
+
   fn or(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
     let left = ExprParser::and(tokens, index);
     
@@ -169,7 +233,7 @@ impl ExprParser {
 
     let mut expression = left.unwrap();
     loop {
-      match ParserUtils::match_advance(tokens, index, &[TokenType::Slash, TokenType::Star]) {
+      match ParserUtils::match_advance(tokens, index, &[TokenType::Slash, TokenType::Star, TokenType::Percent, TokenType::TildeSlash]) {
         Some(token) => {
           let right = ExprParser::unary(tokens, index);
 
@@ -187,6 +251,13 @@ impl ExprParser {
   }
 
   fn unary(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
+    if let Some(operator) = ParserUtils::match_advance(tokens, index, &[TokenType::PlusPlus, TokenType::MinusMinus]) {
+      return match ExprParser::unary(tokens, index)? {
+        Expression::Identifier(target, _resolved) => Ok(Expression::Update(target, operator, false, None)),
+        expr => Err(ParserError::InvalidAssignment(format!("{}{}", operator.lexeme, expr.to_string())))
+      };
+    }
+
     match ParserUtils::match_advance(tokens, index, &[TokenType::Bang, TokenType::Minus]) {
       Some(token) => {
         let right = ExprParser::unary(tokens, index);
@@ -197,7 +268,32 @@ impl ExprParser {
 
         Ok(Expression::Unary(token, Box::new(right.unwrap())))
       },
-      None => ExprParser::call(tokens, index)
+      None => ExprParser::exponent(tokens, index)
+    }
+  }
+
+  // Binds tighter than unary minus (`-2 ** 2` is -4) and is right-associative (`2 ** 3 ** 2` is 2 ** 9).
+  fn exponent(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
+    let base = ExprParser::postfix(tokens, index)?;
+
+    match ParserUtils::match_advance(tokens, index, &[TokenType::StarStar]) {
+      Some(token) => {
+        let exponent = ExprParser::unary(tokens, index)?;
+        Ok(Expression::Binary(Box::new(base), token, Box::new(exponent)))
+      },
+      None => Ok(base)
+    }
+  }
+
+  fn postfix(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
+    let expr = ExprParser::call(tokens, index)?;
+
+    match ParserUtils::match_advance(tokens, index, &[TokenType::PlusPlus, TokenType::MinusMinus]) {
+      Some(operator) => match expr {
+        Expression::Identifier(target, _resolved) => Ok(Expression::Update(target, operator, true, None)),
+        _ => Err(ParserError::InvalidAssignment(format!("{}{}", expr.to_string(), operator.lexeme)))
+      },
+      None => Ok(expr)
     }
   }
 
@@ -205,6 +301,16 @@ impl ExprParser {
     match ExprParser::primary(tokens, index) {
       Ok(mut expr) => {
         loop {
+          if ParserUtils::match_advance(tokens, index, &[TokenType::Dot]).is_some() {
+            match ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
+              Some(name) => {
+                expr = Expression::Get(Box::new(expr), name);
+                continue;
+              },
+              None => return Err(ParserError::ExpectExpression(String::from("Expected property name after '.'.")))
+            }
+          }
+
           match ParserUtils::match_advance(tokens, index, &[TokenType::LeftParen]) {
             Some(_lp) => {
               match ExprParser::arguments(tokens, index) {
@@ -252,16 +358,65 @@ impl ExprParser {
     Ok(arguments)
   }
 
+  // Each `${...}` is tokenized and parsed on its own, keeping the lines of the enclosing source.
+  fn interpolation(token: Rc<Token>) -> Result<Expression, ParserError> {
+    let mut parts: Vec<Expression> = Vec::new();
+
+    for part in token.string_parts() {
+      match part {
+        StringPart::Text(text) => parts.push(Expression::Literal(Rc::new(Token::t_literal(format!("\"{}\"", text), text.clone(), token.line)), RuntimeType::String(text))),
+        StringPart::Code(source, line) => {
+          let (code_tokens, errors) = Token::tokenize(&source);
+          if !errors.is_empty() {
+            return Err(ParserError::ExpectExpression(format!("Invalid interpolation in {}: {}", token.lexeme, errors.join(", "))));
+          }
+
+          let code_tokens: Vec<Rc<Token>> = code_tokens.iter()
+            .map(|t| Rc::new(Token { line: t.line + line - 1, ..(**t).clone() }))
+            .collect();
+
+          let code_index = &mut 0;
+          let expression = ExprParser::expression(&code_tokens, code_index)?;
+          if code_tokens[*code_index].token_type != TokenType::EOF {
+            return Err(ParserError::ExpectExpression(format!("Unexpected '{}' in interpolation.", code_tokens[*code_index].lexeme)));
+          }
+
+          parts.push(expression);
+        }
+      }
+    }
+
+    Ok(Expression::Interpolation(token, parts))
+  }
+
+  // The value a literal token stands for, computed once here rather than on every evaluation.
+  fn literal(token: &Token) -> RuntimeType {
+    match token.token_type {
+      TokenType::Nil => RuntimeType::Nil(),
+      TokenType::True | TokenType::False => RuntimeType::Boolean(token.lexeme.parse::<bool>().unwrap()),
+      TokenType::Number => match RuntimeNumber::parse_integer(&token.lexeme) {
+        Some(i) => RuntimeType::Integer(i),
+        None => RuntimeType::Number(token.literal.parse::<f64>().unwrap())
+      },
+      TokenType::BigInt => RuntimeType::BigInt(Rc::new(RuntimeBigInt::parse(&token.literal).unwrap_or(RuntimeBigInt::zero()))),
+      TokenType::String => RuntimeType::String(token.literal.to_string()),
+      _ => RuntimeType::Nil()
+    }
+  }
+
   fn primary(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Expression, ParserError> {
-    match ParserUtils::match_advance(tokens, index, &[TokenType::False, TokenType::True, TokenType:: Nil, TokenType::Number, TokenType::String]) {
-      Some(token) => return Ok(Expression::Literal(token)),
-      None => {}
-    };
+    if let Some(token) = ParserUtils::match_advance(tokens, index, &[TokenType::False, TokenType::True, TokenType:: Nil, TokenType::Number, TokenType::BigInt, TokenType::String]) {
+      let value = ExprParser::literal(&token);
+      return Ok(Expression::Literal(token, value));
+    }
 
-    match ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
-      Some(token) => return Ok(Expression::Identifier(token)),
-      None => {}
-    };
+    if let Some(token) = ParserUtils::match_advance(tokens, index, &[TokenType::Interpolation]) {
+      return ExprParser::interpolation(token);
+    }
+
+    if let Some(token) = ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
+      return Ok(Expression::Identifier(token, None));
+    }
 
     match ParserUtils::match_advance(tokens, index, &[TokenType::LeftParen]) {
       Some(_) => {
@@ -1,33 +1,82 @@
 use std::cell::RefCell;
 use std::env;
 use std::fs;
-use std::io::{self, Write};
 use std::process;
 use std::rc::Rc;
-use std::time::SystemTime;
-use std::time::UNIX_EPOCH;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
-mod token; use runtime::runtime_function::RuntimeFunction;
-use runtime::runtime_type::RuntimeType;
-use token::Token;
+mod token; use token::Token;
 mod parser; use parser::Parser;
 mod statement; use statement::Statement; use statement::evaluator::StmtEvaluator;
 mod expression; use expression::Expression; use expression::evaluator::ExprEvaluator;
 mod environment; use environment::Environment;
-mod runtime;
+mod runtime; use runtime::call_stack::{CallStack, DEFAULT_MAX_DEPTH}; use runtime::runtime_hook::RuntimeHooks; use runtime::runtime_error::RuntimeError; use runtime::garbage_collector::GarbageCollector;
+mod debugger; use debugger::Debugger;
+mod dap; use dap::DapServer;
+mod profiler; use profiler::Profiler;
+mod coverage; use coverage::Coverage;
+mod native; use native::Natives; use native::native_system::NativeSystem;
+mod module; use module::ModuleLoader;
+mod optimizer; use optimizer::{Optimizer, DEFAULT_OPT_LEVEL};
+mod resolver;
+mod sandbox; use sandbox::{Capability, Sandbox, SandboxConfig};
+mod test_runner; use test_runner::TestRunner; use test_runner::test_report::{ReportFormat, TestReport};
 
+// Rust stack reserved per Lox call; enough for a call made from a deeply nested expression in a debug build.
+const STACK_PER_CALL: usize = 256 * 1024;
+
+// Lox calls recurse on the Rust stack, so the interpreter runs on a thread whose stack fits `--max-depth` calls.
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let (options, _script_args) = split_script_args(args.get(3..).unwrap_or(&[]));
+
+    let max_depth = numeric_flag(options, "--max-depth").unwrap_or(DEFAULT_MAX_DEPTH);
+    let sandbox = sandbox_config(options);
+    let opt_level = opt_level(options);
+
+    let interpreter = thread::Builder::new()
+        .stack_size(max_depth.saturating_mul(STACK_PER_CALL).saturating_add(16 * 1024 * 1024))
+        .spawn(move || {
+            CallStack::set_max_depth(max_depth);
+            Sandbox::enable(sandbox);
+            Optimizer::set_level(opt_level);
+            cli(args);
+        });
+
+    match interpreter.map(|handle| handle.join()) {
+        Ok(Ok(())) => {},
+        Ok(Err(_panic)) => process::exit(101),
+        Err(e) => {
+            eprintln!("Failed to start the interpreter with --max-depth {}: {}", max_depth, e);
+            process::exit(71);
+        }
+    }
+}
+
+fn cli(args: Vec<String>) {
+    if args.len() == 2 && args[1] == "dap" {
+        let env = Rc::new(RefCell::new(Environment::global()));
+        Natives::define_all(&env);
+        let _globals = GarbageCollector::root_env(&env);
+        DapServer::serve(&env);
+        return;
+    }
+
     if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
+        eprintln!("Usage: {} tokenize <filename>", args[0]);
         return;
     }
 
     let command = &args[1];
     let filename = &args[2];
+    let (options, script_args) = split_script_args(&args[3..]);
+    NativeSystem::set_args(script_args);
 
-    let mut env = Rc::new(RefCell::new(Environment::global()));
-    define_native_funcs(&env);
+    let env = Rc::new(RefCell::new(Environment::global()));
+    Natives::define_all(&env);
+    let _globals = GarbageCollector::root_env(&env);
 
     match command.as_str() {
         "tokenize" => {
@@ -38,36 +87,32 @@ fn main() {
             parse_expr(&tokens, true);
         },
         "evaluate" => {
-            evaluate_expr(filename, &mut env);
+            evaluate_expr(filename, &env);
         },
         "run" => {
-            run(filename, &mut env);
+            run(filename, &env, options);
+        },
+        "optimize" => {
+            let tokens = tokenize(filename, false);
+            for statement in Optimizer::optimize(parse_stmt(&tokens, false)) {
+                println!("{}", statement.to_string())
+            }
+        },
+        "debug" => {
+            debug(filename, &env);
+        },
+        "test" => {
+            test(filename, options);
         }
         _ => {
-            writeln!(io::stderr(), "Unknown command: {}", command).unwrap();
-            return;
+            eprintln!("Unknown command: {}", command);
         }
     }
 }
 
-fn define_native_funcs(env: &Rc<RefCell<Environment>>) {
-    let fun = RuntimeFunction::new(
-        String::from("clock"), 
-        vec![], 
-        Box::new(Statement::Native(Rc::new(|| {
-            return Ok(RuntimeType::Number(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64().floor()));
-        }))), 
-        env.clone()
-    );
-
-    let fun_type = RuntimeType::Function(Rc::new(fun));
-
-    env.borrow_mut().define(String::from("clock"), Rc::new(fun_type));
-}
-
 fn tokenize(filename: &String, print_tokens: bool) -> Vec<Rc<Token>> {
     let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-        writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
+        eprintln!("Failed to read file {}", filename);
         String::new()
     });
 
@@ -83,7 +128,7 @@ fn tokenize(filename: &String, print_tokens: bool) -> Vec<Rc<Token>> {
         }
     }
 
-    if errors.len() > 0 {
+    if !errors.is_empty() {
         process::exit(65);
     }
 
@@ -98,17 +143,17 @@ fn parse_expr(tokens: &Vec<Rc<Token>>, print_expr: bool) -> Expression {
             if print_expr {
                 println!("{}", e.to_string())
             }
-            
-            return e;
+
+            e
         },
         Err(e) => {
             eprintln!("{}", e.to_string());
             process::exit(65);
         }
-    };
+    }
 }
 
-fn evaluate_expr<'a>(filename: &String, env: &Rc<RefCell<Environment>>) {
+fn evaluate_expr(filename: &String, env: &Rc<RefCell<Environment>>) {
     let tokens = tokenize(filename,  false);
     let expression = parse_expr(&tokens,  false);
 
@@ -132,27 +177,213 @@ fn parse_stmt(tokens: &Vec<Rc<Token>>, print: bool) -> Vec<Statement> {
                     println!("{}", statement.to_string())
                 }
             }
-            
-            return statements;
+
+            statements
         },
         Err(e) => {
             eprintln!("{}", e.to_string());
             process::exit(65);
         }
+    }
+}
+
+fn run(filename: &String, env: &Rc<RefCell<Environment>>, options: &[String]) {
+    // Coverage reports the branches as written, so the program runs unoptimized.
+    if has_flag(options, "--coverage") {
+        Optimizer::set_level(0);
+    }
+
+    let tokens = tokenize(filename, false);
+    let statements = Optimizer::optimize(parse_stmt(&tokens, false));
+
+    let profiler = if has_flag(options, "--profile") {
+        let profiler = Profiler::new();
+        RuntimeHooks::install(Box::new(profiler.clone()));
+        Some(profiler)
+    } else {
+        None
     };
+
+    let coverage = if has_flag(options, "--coverage") {
+        let coverage = Coverage::new(&statements);
+        RuntimeHooks::install(Box::new(coverage.clone()));
+        Some(coverage)
+    } else {
+        None
+    };
+
+    ModuleLoader::push_file(filename);
+    let result = execute(&statements, env);
+    ModuleLoader::pop_file();
+
+    if has_flag(options, "--gc-stats") {
+        GarbageCollector::collect();
+        eprintln!("{}", GarbageCollector::stats());
+    }
+
+    if let Some(coverage) = coverage {
+        eprintln!("{}", coverage.summary());
+
+        let path = flag_value(options, "--coverage-output").map(|p| p.as_str()).unwrap_or("lcov.info");
+        if let Err(e) = coverage.write_lcov(filename, path) {
+            eprintln!("Failed to write coverage to {}: {}", path, e);
+        }
+    }
+
+    if let Some(profiler) = profiler {
+        profiler.stop();
+        profiler.report();
+
+        if let Some(path) = flag_value(options, "--profile-output") {
+            if let Err(e) = profiler.write_collapsed(path) {
+                eprintln!("Failed to write profile to {}: {}", path, e);
+            }
+        }
+    }
+
+    exit_on_error(result);
 }
 
-fn run(filename: &String, env: &Rc<RefCell<Environment>>) {
+fn debug(filename: &String, env: &Rc<RefCell<Environment>>) {
+    // Stepping follows the source, so nothing is folded away.
+    Optimizer::set_level(0);
+
     let tokens = tokenize(filename, false);
     let statements = parse_stmt(&tokens, false);
 
+    RuntimeHooks::install(Box::new(Debugger::new()));
+    eprintln!("Debugging {}. Type 'help' for a list of commands.", filename);
+
+    ModuleLoader::push_file(filename);
+    exit_on_error(execute(&statements, env));
+}
+
+fn test(dir: &str, options: &[String]) {
+    let format_name = flag_value(options, "--format").map(|f| f.as_str()).unwrap_or("pretty");
+    let format = match ReportFormat::parse(format_name) {
+        Some(format) => format,
+        None => {
+            eprintln!("Unknown test report format: {}. Expected pretty, tap or junit.", format_name);
+            process::exit(64);
+        }
+    };
+
+    let results = match TestRunner::new().run(dir) {
+        Ok(results) => results,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(66);
+        }
+    };
+    let report = TestReport::render(&results, &format);
+
+    match flag_value(options, "--output") {
+        Some(path) => {
+            if let Err(e) = fs::write(path, report) {
+                eprintln!("Failed to write test report to {}: {}", path, e);
+                process::exit(74);
+            }
+        },
+        None => print!("{}", report)
+    }
+
+    if TestReport::failed(&results) > 0 {
+        process::exit(1);
+    }
+}
+
+fn execute(statements: &Vec<Statement>, env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
     for statement in statements {
-        match StmtEvaluator::evaluate(&statement, env) {
-            Ok(_r) => {},
-            Err(e) => {
-                eprintln!("{}", e.to_string());
-                process::exit(70);
+        StmtEvaluator::evaluate(statement, env)?;
+    }
+
+    Ok(())
+}
+
+fn exit_on_error(result: Result<(), RuntimeError>) {
+    match result {
+        Ok(()) => {},
+        Err(RuntimeError::Exit(code)) => process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e.to_string());
+            process::exit(70);
+        }
+    }
+}
+
+// Interpreter options come first; everything after `--` is passed to the script as `args`.
+fn split_script_args(args: &[String]) -> (&[String], &[String]) {
+    match args.iter().position(|a| a == "--") {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, &[])
+    }
+}
+
+// `--sandbox` starts from restrictive defaults; the individual limits and `--allow` work with or without it.
+fn sandbox_config(options: &[String]) -> SandboxConfig {
+    let mut config = if has_flag(options, "--sandbox") { SandboxConfig::restricted() } else { SandboxConfig::default() };
+
+    if let Some(max_steps) = numeric_flag(options, "--max-steps") {
+        config.max_steps = Some(max_steps);
+    }
+    if let Some(timeout_ms) = numeric_flag(options, "--timeout-ms") {
+        config.timeout = Some(Duration::from_millis(timeout_ms));
+    }
+    if let Some(max_allocations) = numeric_flag(options, "--max-allocations") {
+        config.max_allocations = Some(max_allocations);
+    }
+    if let Some(max_string_length) = numeric_flag(options, "--max-string-length") {
+        config.max_string_length = Some(max_string_length);
+    }
+
+    if let Some(allowed) = flag_value(options, "--allow") {
+        let mut capabilities = Vec::new();
+        for name in allowed.split(',').filter(|n| !n.is_empty()) {
+            match Capability::parse(name) {
+                Some(capability) => capabilities.push(capability),
+                None => {
+                    eprintln!("Unknown capability: {}. Expected io, env, clock or import.", name);
+                    process::exit(64);
+                }
             }
         }
+        config.capabilities = Some(capabilities);
     }
+
+    config
+}
+
+// 0 runs the program as parsed, 1 folds constant expressions, 2 also removes branches that can never run.
+fn opt_level(options: &[String]) -> u8 {
+    match flag_value(options, "--opt-level") {
+        Some(value) => match value.parse::<u8>() {
+            Ok(level) if level <= 2 => level,
+            _ => {
+                eprintln!("Invalid --opt-level: {}. Expected 0, 1 or 2.", value);
+                process::exit(64);
+            }
+        },
+        None => DEFAULT_OPT_LEVEL
+    }
+}
+
+// Value of a flag that takes a positive number. Exits on anything else.
+fn numeric_flag<T: FromStr + PartialOrd + Default>(options: &[String], flag: &str) -> Option<T> {
+    let value = flag_value(options, flag)?;
+
+    match value.parse::<T>() {
+        Ok(number) if number > T::default() => Some(number),
+        _ => {
+            eprintln!("Invalid {}: {}. Expected a positive number.", flag, value);
+            process::exit(64);
+        }
+    }
+}
+
+fn has_flag(options: &[String], flag: &str) -> bool {
+    options.iter().any(|o| o == flag)
+}
+
+fn flag_value<'a>(options: &'a [String], flag: &str) -> Option<&'a String> {
+    options.iter().position(|o| o == flag).and_then(|i| options.get(i + 1))
 }
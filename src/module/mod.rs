@@ -0,0 +1,158 @@
+use std::{cell::RefCell, collections::HashMap, fs, path::{Path, PathBuf}, rc::Rc};
+
+use crate::environment::Environment;
+use crate::native::Natives;
+use crate::optimizer::Optimizer;
+use crate::parser::Parser;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_module::RuntimeModule;
+use crate::sandbox::Sandbox;
+use crate::statement::evaluator::StmtEvaluator;
+use crate::token::Token;
+
+struct LoaderState {
+  // Modules already evaluated, keyed by canonical path.
+  cache: HashMap<PathBuf, Rc<RuntimeModule>>,
+  // Files currently being evaluated, outermost first.
+  files: Vec<Rc<PathBuf>>,
+  // The file whose code is running: the one being evaluated, or the file of the function being called.
+  current: Option<Rc<PathBuf>>,
+  // The outermost file of the last run, which stays known after it finished for the reports.
+  main: Option<Rc<PathBuf>>
+}
+
+thread_local! {
+  static LOADER: RefCell<LoaderState> = RefCell::new(LoaderState { cache: HashMap::new(), files: Vec::new(), current: None, main: None });
+}
+
+// Loads `import`ed files. Each file is evaluated once into its own global environment and cached.
+pub struct ModuleLoader;
+
+impl ModuleLoader {
+  // Marks a script as being evaluated, so that its imports resolve relative to it.
+  pub fn push_file(path: &str) {
+    let path = Rc::new(fs::canonicalize(path).unwrap_or(PathBuf::from(path)));
+    LOADER.with(|loader| {
+      let mut loader = loader.borrow_mut();
+      if loader.files.is_empty() {
+        loader.main = Some(path.clone());
+      }
+      loader.files.push(path.clone());
+      loader.current = Some(path);
+    });
+  }
+
+  pub fn pop_file() {
+    LOADER.with(|loader| {
+      let mut loader = loader.borrow_mut();
+      loader.files.pop();
+      loader.current = loader.files.last().cloned();
+    });
+  }
+
+  pub fn current_file() -> Option<Rc<PathBuf>> {
+    LOADER.with(|loader| loader.borrow().current.clone())
+  }
+
+  // Returns the file that was running before.
+  pub fn set_current_file(file: Option<Rc<PathBuf>>) -> Option<Rc<PathBuf>> {
+    LOADER.with(|loader| std::mem::replace(&mut loader.borrow_mut().current, file))
+  }
+
+  pub fn main_file() -> Option<Rc<PathBuf>> {
+    LOADER.with(|loader| loader.borrow().main.clone())
+  }
+
+  pub fn in_main_file() -> bool {
+    LOADER.with(|loader| {
+      let loader = loader.borrow();
+      match (&loader.current, &loader.main) {
+        (Some(current), Some(main)) => Rc::ptr_eq(current, main),
+        _ => false
+      }
+    })
+  }
+
+  pub fn is_main_file(file: Option<&Rc<PathBuf>>) -> bool {
+    LOADER.with(|loader| match (file, &loader.borrow().main) {
+      (Some(file), Some(main)) => Rc::ptr_eq(file, main),
+      _ => false
+    })
+  }
+
+  // Modules stay cached for the rest of the run, so the garbage collector treats them as roots.
+  pub fn modules() -> Vec<Rc<RuntimeModule>> {
+    LOADER.with(|loader| loader.borrow().cache.values().cloned().collect())
+  }
+
+  pub fn load(path: &str) -> Result<Rc<RuntimeModule>, RuntimeError> {
+    Sandbox::check_import(path)?;
+    let resolved = ModuleLoader::resolve(path)?;
+
+    let cached = LOADER.with(|loader| loader.borrow().cache.get(&resolved).cloned());
+    if let Some(module) = cached {
+      return Ok(module);
+    }
+
+    let cycle = LOADER.with(|loader| {
+      let loader = loader.borrow();
+      loader.files.iter().position(|f| **f == resolved).map(|start| {
+        let mut chain: Vec<String> = loader.files[start..].iter().map(|f| ModuleLoader::display(f)).collect();
+        chain.push(ModuleLoader::display(&resolved));
+        chain.join(" -> ")
+      })
+    });
+    if let Some(chain) = cycle {
+      return Err(RuntimeError::ImportError(format!("Import cycle detected: {}", chain)));
+    }
+
+    let source = fs::read_to_string(&resolved)
+      .map_err(|e| RuntimeError::ImportError(format!("Failed to read {}: {}", path, e)))?;
+
+    let (tokens, errors) = Token::tokenize(&source);
+    if !errors.is_empty() {
+      return Err(RuntimeError::ImportError(format!("{}\n{}", path, errors.join("\n"))));
+    }
+
+    let statements = Parser::parse_statements(&tokens)
+      .map_err(|e| RuntimeError::ImportError(format!("{}\n{}", path, e.to_string())))?;
+    let statements = Optimizer::optimize(statements);
+
+    // The natives live one scope up so that only the module's own declarations are exported.
+    let builtins = Rc::new(RefCell::new(Environment::global()));
+    Natives::define_all(&builtins);
+    let env = Rc::new(RefCell::new(Environment::local(builtins)));
+
+    let file = Rc::new(resolved.clone());
+    LOADER.with(|loader| loader.borrow_mut().files.push(file.clone()));
+    let previous_file = ModuleLoader::set_current_file(Some(file));
+    let mut result = Ok(());
+    for statement in &statements {
+      if let Err(e) = StmtEvaluator::evaluate(statement, &env) {
+        result = Err(e);
+        break;
+      }
+    }
+    LOADER.with(|loader| loader.borrow_mut().files.pop());
+    ModuleLoader::set_current_file(previous_file);
+    result?;
+
+    let module = Rc::new(RuntimeModule::new(path.to_owned(), env));
+    LOADER.with(|loader| loader.borrow_mut().cache.insert(resolved, module.clone()));
+
+    Ok(module)
+  }
+
+  // Relative paths are resolved against the directory of the importing file.
+  fn resolve(path: &str) -> Result<PathBuf, RuntimeError> {
+    let importer = ModuleLoader::current_file();
+    let base = importer.as_deref().and_then(|f| f.parent()).map(Path::to_path_buf).unwrap_or_default();
+
+    fs::canonicalize(base.join(path))
+      .map_err(|e| RuntimeError::ImportError(format!("Failed to read {}: {}", path, e)))
+  }
+
+  pub fn display(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(path.display().to_string())
+  }
+}
@@ -0,0 +1,66 @@
+pub mod native_assert;
+pub mod native_io;
+pub mod native_math;
+pub mod native_string;
+pub mod native_system;
+
+use std::{cell::RefCell, rc::Rc};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::environment::Environment;
+use crate::runtime::call_stack::CallStack;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_function::RuntimeFunction;
+use crate::runtime::runtime_type::RuntimeType;
+use crate::sandbox::Sandbox;
+use crate::statement::Statement;
+use crate::token::Token;
+
+use native_assert::NativeAssert;
+use native_io::NativeIo;
+use native_math::NativeMath;
+use native_string::NativeString;
+use native_system::NativeSystem;
+
+pub struct Natives;
+
+impl Natives {
+  pub fn define_all(env: &Rc<RefCell<Environment>>) {
+    Natives::define(env, "clock", &[], |_args, _env| {
+      Ok(RuntimeType::Number(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64().floor()))
+    });
+
+    NativeAssert::define(env);
+    NativeMath::define(env);
+    NativeString::define(env);
+    NativeIo::define(env);
+    NativeSystem::define(env);
+  }
+
+  // Registers a native function. Arguments are bound to `params` in the call's local environment like for any other
+  // function, and handed to `fun` in order.
+  pub fn define<F>(env: &Rc<RefCell<Environment>>, name: &str, params: &'static [&'static str], fun: F)
+  where F: Fn(&[Rc<RuntimeType>], &Rc<RefCell<Environment>>) -> Result<RuntimeType, RuntimeError> + 'static {
+    let args: Vec<Rc<Token>> = params.iter().map(|p| Rc::new(Token::t_identifier(p.to_string(), 0))).collect();
+
+    let native_name = name.to_owned();
+    let body = Statement::Native(Rc::new(move |local_env: &Rc<RefCell<Environment>>| {
+      Sandbox::check_native(&native_name)?;
+
+      // The arguments are the first slots of the call's scope.
+      let values: Vec<Rc<RuntimeType>> = (0..params.len())
+        .map(|slot| local_env.borrow().get_at(0, slot).unwrap_or(Rc::new(RuntimeType::Nil())))
+        .collect();
+
+      fun(&values, local_env)
+    }));
+
+    let fun = RuntimeFunction::new(name.to_owned(), args, Box::new(body), env.clone(), Vec::new());
+    env.borrow_mut().define_builtin(name, Rc::new(RuntimeType::Function(Rc::new(fun))));
+  }
+
+  // Line of the expression that called the currently running native.
+  pub fn call_line() -> usize {
+    CallStack::frames().first().map(|f| f.call_line).unwrap_or(0)
+  }
+}
@@ -0,0 +1,54 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::environment::Environment;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_type::RuntimeType;
+
+use super::Natives;
+
+// Assertions used by the `test` command. Failures carry the line of the assertion call.
+pub struct NativeAssert;
+
+impl NativeAssert {
+  pub fn define(env: &Rc<RefCell<Environment>>) {
+    Natives::define(env, "assert", &["condition"], |args, _env| {
+      if args[0].is_truthy() {
+        return Ok(RuntimeType::Nil());
+      }
+
+      Err(RuntimeError::AssertionFailed(format!("expected a truthy value, got {}", NativeAssert::describe(&args[0])), Natives::call_line()))
+    });
+
+    Natives::define(env, "assert_eq", &["expected", "actual"], |args, _env| {
+      if args[0].equals(&args[1]) {
+        return Ok(RuntimeType::Nil());
+      }
+
+      let message = format!("expected: {}, actual: {}", NativeAssert::describe(&args[0]), NativeAssert::describe(&args[1]));
+      Err(RuntimeError::AssertionFailed(message, Natives::call_line()))
+    });
+
+    // Returns the error message so tests can check it.
+    Natives::define(env, "assert_throws", &["function"], |args, env| {
+      let line = Natives::call_line();
+
+      let function = match args[0].as_ref() {
+        RuntimeType::Function(function) => function.clone(),
+        other => return Err(RuntimeError::InvalidArgument(String::from("assert_throws"), format!("expected a function, got {}", NativeAssert::describe(other))))
+      };
+
+      match function.call(vec![], line, env) {
+        Ok(value) => Err(RuntimeError::AssertionFailed(format!("expected <fn {}> to throw, it returned {}", function.get_name(), NativeAssert::describe(&value)), line)),
+        Err(e) if e.is_fatal() => Err(e),
+        Err(e) => Ok(RuntimeType::String(e.to_string()))
+      }
+    });
+  }
+
+  fn describe(value: &RuntimeType) -> String {
+    match value {
+      RuntimeType::String(s) => format!("\"{}\" ({})", s, value.type_name()),
+      _ => format!("{} ({})", value.to_string(), value.type_name())
+    }
+  }
+}
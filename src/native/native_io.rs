@@ -0,0 +1,106 @@
+use std::{cell::RefCell, fs, io::{self, BufRead, Read, Write}, path::Path, rc::Rc};
+
+use crate::environment::Environment;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_type::RuntimeType;
+use crate::sandbox::Sandbox;
+
+use super::Natives;
+
+// File and stdin access. Failures raise an `IoError` naming the native and the path.
+pub struct NativeIo;
+
+impl NativeIo {
+  pub fn define(env: &Rc<RefCell<Environment>>) {
+    // One line without its line ending, or nil at the end of the input.
+    Natives::define(env, "read_line", &[], |_args, _env| {
+      let mut line = String::new();
+
+      match io::stdin().lock().read_line(&mut line) {
+        Ok(0) => Ok(RuntimeType::Nil()),
+        Ok(_) => {
+          let trimmed = line.strip_suffix('\n').unwrap_or(&line);
+          Ok(RuntimeType::String(trimmed.strip_suffix('\r').unwrap_or(trimmed).to_owned()))
+        },
+        Err(e) => Err(RuntimeError::IoError(String::from("read_line"), e.to_string()))
+      }
+    });
+
+    Natives::define(env, "read_all_stdin", &[], |_args, _env| {
+      let mut input = String::new();
+
+      match io::stdin().lock().read_to_string(&mut input) {
+        Ok(_) => {
+          Sandbox::check_string(input.chars().count())?;
+          Ok(RuntimeType::String(input))
+        },
+        Err(e) => Err(RuntimeError::IoError(String::from("read_all_stdin"), e.to_string()))
+      }
+    });
+
+    Natives::define(env, "read_file", &["path"], |args, _env| {
+      let path = NativeIo::path("read_file", &args[0])?;
+
+      match fs::read_to_string(path) {
+        Ok(content) => {
+          Sandbox::check_string(content.chars().count())?;
+          Ok(RuntimeType::String(content))
+        },
+        Err(e) => Err(RuntimeError::IoError(String::from("read_file"), format!("{}: {}", path, e)))
+      }
+    });
+
+    Natives::define(env, "write_file", &["path", "content"], |args, _env| {
+      let path = NativeIo::path("write_file", &args[0])?;
+
+      match fs::write(path, args[1].to_string()) {
+        Ok(()) => Ok(RuntimeType::Nil()),
+        Err(e) => Err(RuntimeError::IoError(String::from("write_file"), format!("{}: {}", path, e)))
+      }
+    });
+
+    Natives::define(env, "append_file", &["path", "content"], |args, _env| {
+      let path = NativeIo::path("append_file", &args[0])?;
+
+      let result = fs::OpenOptions::new().create(true).append(true).open(path)
+        .and_then(|mut file| file.write_all(args[1].to_string().as_bytes()));
+
+      match result {
+        Ok(()) => Ok(RuntimeType::Nil()),
+        Err(e) => Err(RuntimeError::IoError(String::from("append_file"), format!("{}: {}", path, e)))
+      }
+    });
+
+    Natives::define(env, "file_exists", &["path"], |args, _env| {
+      Ok(RuntimeType::Boolean(Path::new(NativeIo::path("file_exists", &args[0])?).exists()))
+    });
+
+    // Entry names, sorted.
+    Natives::define(env, "list_dir", &["path"], |args, _env| {
+      let path = NativeIo::path("list_dir", &args[0])?;
+
+      let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => return Err(RuntimeError::IoError(String::from("list_dir"), format!("{}: {}", path, e)))
+      };
+
+      let mut names: Vec<String> = Vec::new();
+      for entry in entries {
+        match entry {
+          Ok(entry) => names.push(entry.file_name().to_string_lossy().to_string()),
+          Err(e) => return Err(RuntimeError::IoError(String::from("list_dir"), format!("{}: {}", path, e)))
+        }
+      }
+      names.sort();
+
+      Ok(RuntimeType::List(Rc::new(RefCell::new(names.into_iter().map(RuntimeType::String).collect()))))
+    });
+  }
+
+  fn path<'a>(name: &str, value: &'a RuntimeType) -> Result<&'a str, RuntimeError> {
+    match value {
+      RuntimeType::String(s) => Ok(s),
+      _ => Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a path string, got {}", value.type_name())))
+    }
+  }
+}
@@ -0,0 +1,153 @@
+use std::{cell::RefCell, f64::consts, rc::Rc};
+
+use crate::environment::Environment;
+use crate::runtime::runtime_bigint::RuntimeBigInt;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_type::RuntimeType;
+
+use super::Natives;
+
+// Math functions and constants. Arguments are checked, so a bad call is a runtime error rather than a NaN.
+pub struct NativeMath;
+
+impl NativeMath {
+  pub fn define(env: &Rc<RefCell<Environment>>) {
+    env.borrow_mut().define_builtin("PI", Rc::new(RuntimeType::Number(consts::PI)));
+    env.borrow_mut().define_builtin("E", Rc::new(RuntimeType::Number(consts::E)));
+
+    Natives::define(env, "abs", &["x"], |args, _env| {
+      match args[0].as_ref() {
+        RuntimeType::Integer(i) => match i.checked_abs() {
+          Some(abs) => Ok(RuntimeType::Integer(abs)),
+          None => Err(RuntimeError::InvalidArgument(String::from("abs"), format!("{} has no integer absolute value", i)))
+        },
+        _ => Ok(RuntimeType::Number(NativeMath::number("abs", &args[0])?.abs()))
+      }
+    });
+
+    NativeMath::unary(env, "floor", f64::floor);
+    NativeMath::unary(env, "ceil", f64::ceil);
+    // Halfway cases round away from zero.
+    NativeMath::unary(env, "round", f64::round);
+    NativeMath::unary(env, "trunc", f64::trunc);
+    NativeMath::unary(env, "sin", f64::sin);
+    NativeMath::unary(env, "cos", f64::cos);
+    NativeMath::unary(env, "tan", f64::tan);
+    NativeMath::unary(env, "atan", f64::atan);
+    NativeMath::unary(env, "exp", f64::exp);
+
+    Natives::define(env, "sqrt", &["x"], |args, _env| {
+      let x = NativeMath::number("sqrt", &args[0])?;
+      if x < 0. {
+        return Err(RuntimeError::InvalidArgument(String::from("sqrt"), format!("expected a non-negative number, got {}", x)));
+      }
+      Ok(RuntimeType::Number(x.sqrt()))
+    });
+
+    Natives::define(env, "asin", &["x"], |args, _env| {
+      Ok(RuntimeType::Number(NativeMath::unit_range("asin", &args[0])?.asin()))
+    });
+
+    Natives::define(env, "acos", &["x"], |args, _env| {
+      Ok(RuntimeType::Number(NativeMath::unit_range("acos", &args[0])?.acos()))
+    });
+
+    Natives::define(env, "atan2", &["y", "x"], |args, _env| {
+      let y = NativeMath::number("atan2", &args[0])?;
+      let x = NativeMath::number("atan2", &args[1])?;
+      Ok(RuntimeType::Number(y.atan2(x)))
+    });
+
+    Natives::define(env, "log", &["x"], |args, _env| {
+      Ok(RuntimeType::Number(NativeMath::positive("log", &args[0])?.ln()))
+    });
+
+    Natives::define(env, "log2", &["x"], |args, _env| {
+      Ok(RuntimeType::Number(NativeMath::positive("log2", &args[0])?.log2()))
+    });
+
+    Natives::define(env, "log10", &["x"], |args, _env| {
+      Ok(RuntimeType::Number(NativeMath::positive("log10", &args[0])?.log10()))
+    });
+
+    Natives::define(env, "pow", &["base", "exponent"], |args, _env| {
+      let base = NativeMath::number("pow", &args[0])?;
+      let exponent = NativeMath::number("pow", &args[1])?;
+      Ok(RuntimeType::Number(base.powf(exponent)))
+    });
+
+    Natives::define(env, "min", &["a", "b"], |args, _env| {
+      if let (RuntimeType::Integer(a), RuntimeType::Integer(b)) = (args[0].as_ref(), args[1].as_ref()) {
+        return Ok(RuntimeType::Integer(*a.min(b)));
+      }
+
+      let a = NativeMath::number("min", &args[0])?;
+      let b = NativeMath::number("min", &args[1])?;
+      Ok(RuntimeType::Number(a.min(b)))
+    });
+
+    Natives::define(env, "max", &["a", "b"], |args, _env| {
+      if let (RuntimeType::Integer(a), RuntimeType::Integer(b)) = (args[0].as_ref(), args[1].as_ref()) {
+        return Ok(RuntimeType::Integer(*a.max(b)));
+      }
+
+      let a = NativeMath::number("max", &args[0])?;
+      let b = NativeMath::number("max", &args[1])?;
+      Ok(RuntimeType::Number(a.max(b)))
+    });
+
+    // From a decimal string ("-123"), an integer or an integral number.
+    Natives::define(env, "bigint", &["value"], |args, _env| {
+      let value = match args[0].as_ref() {
+        RuntimeType::BigInt(i) => Some((**i).clone()),
+        RuntimeType::Integer(i) => Some(RuntimeBigInt::from_i64(*i)),
+        RuntimeType::Number(n) => RuntimeBigInt::from_f64(*n),
+        RuntimeType::String(s) => RuntimeBigInt::parse(s.trim()),
+        _ => None
+      };
+
+      match value {
+        Some(value) => Ok(RuntimeType::BigInt(Rc::new(value))),
+        None => Err(RuntimeError::InvalidArgument(String::from("bigint"), format!("cannot convert {} to a bigint", args[0].to_string())))
+      }
+    });
+
+    // Type check rather than a conversion, so any value is accepted.
+    Natives::define(env, "is_integer", &["x"], |args, _env| {
+      match args[0].as_ref() {
+        RuntimeType::Integer(_) | RuntimeType::BigInt(_) => Ok(RuntimeType::Boolean(true)),
+        RuntimeType::Number(n) => Ok(RuntimeType::Boolean(n.is_finite() && n.fract() == 0.)),
+        _ => Ok(RuntimeType::Boolean(false))
+      }
+    });
+  }
+
+  fn unary(env: &Rc<RefCell<Environment>>, name: &'static str, fun: fn(f64) -> f64) {
+    Natives::define(env, name, &["x"], move |args, _env| {
+      Ok(RuntimeType::Number(fun(NativeMath::number(name, &args[0])?)))
+    });
+  }
+
+  fn number(name: &str, value: &RuntimeType) -> Result<f64, RuntimeError> {
+    match value.as_number() {
+      Some(n) => Ok(n),
+      None => Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a number, got {}", value.type_name())))
+    }
+  }
+
+  fn positive(name: &str, value: &RuntimeType) -> Result<f64, RuntimeError> {
+    let x = NativeMath::number(name, value)?;
+    if x <= 0. {
+      return Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a positive number, got {}", x)));
+    }
+    Ok(x)
+  }
+
+  fn unit_range(name: &str, value: &RuntimeType) -> Result<f64, RuntimeError> {
+    let x = NativeMath::number(name, value)?;
+    if !(-1. ..=1.).contains(&x) {
+      return Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a number between -1 and 1, got {}", x)));
+    }
+    Ok(x)
+  }
+}
@@ -0,0 +1,216 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::environment::Environment;
+use crate::runtime::garbage_collector::GarbageCollector;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_type::RuntimeType;
+use crate::sandbox::Sandbox;
+
+use super::Natives;
+
+// String functions, plus the few list functions needed to use `split` and `join`. Lengths and indexes count chars,
+// not bytes, so they behave the same for any Unicode text.
+pub struct NativeString;
+
+impl NativeString {
+  pub fn define(env: &Rc<RefCell<Environment>>) {
+    Natives::define(env, "len", &["value"], |args, _env| {
+      match args[0].as_ref() {
+        RuntimeType::String(s) => Ok(RuntimeType::Integer(s.chars().count() as i64)),
+        RuntimeType::List(l) => Ok(RuntimeType::Integer(l.borrow().len() as i64)),
+        other => Err(RuntimeError::InvalidArgument(String::from("len"), format!("expected a string or a list, got {}", other.type_name())))
+      }
+    });
+
+    // The substring is cut short at the end of the string.
+    Natives::define(env, "substr", &["string", "start", "length"], |args, _env| {
+      let s = NativeString::string("substr", &args[0])?;
+      let start = NativeString::index("substr", &args[1])?;
+      let length = NativeString::index("substr", &args[2])?;
+
+      let count = s.chars().count();
+      if start > count {
+        return Err(RuntimeError::InvalidArgument(String::from("substr"), format!("start {} is past the end of a string of length {}", start, count)));
+      }
+
+      Ok(RuntimeType::String(s.chars().skip(start).take(length).collect()))
+    });
+
+    // Char index of the first match, or -1.
+    Natives::define(env, "index_of", &["string", "search"], |args, _env| {
+      let s = NativeString::string("index_of", &args[0])?;
+      let search = NativeString::string("index_of", &args[1])?;
+
+      match s.find(search) {
+        Some(byte_index) => Ok(RuntimeType::Integer(s[..byte_index].chars().count() as i64)),
+        None => Ok(RuntimeType::Integer(-1))
+      }
+    });
+
+    Natives::define(env, "split", &["string", "separator"], |args, _env| {
+      let s = NativeString::string("split", &args[0])?;
+      let separator = NativeString::string("split", &args[1])?;
+
+      // An empty separator splits into chars.
+      let parts: Vec<RuntimeType> = if separator.is_empty() {
+        s.chars().map(|c| RuntimeType::String(c.to_string())).collect()
+      } else {
+        s.split(separator).map(|p| RuntimeType::String(p.to_owned())).collect()
+      };
+
+      Sandbox::allocate(parts.len() + 1)?;
+      Ok(RuntimeType::List(Rc::new(RefCell::new(parts))))
+    });
+
+    Natives::define(env, "join", &["list", "separator"], |args, _env| {
+      let separator = NativeString::string("join", &args[1])?;
+
+      match args[0].as_ref() {
+        RuntimeType::List(l) => {
+          // Built piece by piece so a huge list runs into the sandbox's limits before it is all joined.
+          let mut joined = String::new();
+          let mut length = 0;
+          for (i, value) in l.borrow().iter().enumerate() {
+            Sandbox::check_time()?;
+            let part = value.to_string();
+            if i > 0 {
+              joined.push_str(separator);
+              length += separator.chars().count();
+            }
+            length += part.chars().count();
+            Sandbox::check_length(length)?;
+            joined.push_str(&part);
+          }
+
+          Sandbox::check_string(length)?;
+          Ok(RuntimeType::String(joined))
+        },
+        other => Err(RuntimeError::InvalidArgument(String::from("join"), format!("expected a list, got {}", other.type_name())))
+      }
+    });
+
+    Natives::define(env, "trim", &["string"], |args, _env| {
+      Ok(RuntimeType::String(NativeString::string("trim", &args[0])?.trim().to_owned()))
+    });
+
+    Natives::define(env, "upper", &["string"], |args, _env| {
+      Ok(RuntimeType::String(NativeString::string("upper", &args[0])?.to_uppercase()))
+    });
+
+    Natives::define(env, "lower", &["string"], |args, _env| {
+      Ok(RuntimeType::String(NativeString::string("lower", &args[0])?.to_lowercase()))
+    });
+
+    Natives::define(env, "replace", &["string", "from", "to"], |args, _env| {
+      let s = NativeString::string("replace", &args[0])?;
+      let from = NativeString::string("replace", &args[1])?;
+      let to = NativeString::string("replace", &args[2])?;
+
+      if from.is_empty() {
+        return Err(RuntimeError::InvalidArgument(String::from("replace"), String::from("the string to replace is empty")));
+      }
+
+      let replaced = s.replace(from, to);
+      Sandbox::check_string(replaced.chars().count())?;
+      Ok(RuntimeType::String(replaced))
+    });
+
+    Natives::define(env, "starts_with", &["string", "prefix"], |args, _env| {
+      let s = NativeString::string("starts_with", &args[0])?;
+      Ok(RuntimeType::Boolean(s.starts_with(NativeString::string("starts_with", &args[1])?)))
+    });
+
+    Natives::define(env, "ends_with", &["string", "suffix"], |args, _env| {
+      let s = NativeString::string("ends_with", &args[0])?;
+      Ok(RuntimeType::Boolean(s.ends_with(NativeString::string("ends_with", &args[1])?)))
+    });
+
+    Natives::define(env, "repeat", &["string", "count"], |args, _env| {
+      let s = NativeString::string("repeat", &args[0])?;
+      let count = NativeString::index("repeat", &args[1])?;
+
+      // Checked up front so a huge count fails before allocating, and fails as an error rather than aborting when
+      // the memory isn't there.
+      let too_large = || RuntimeError::InvalidArgument(String::from("repeat"), format!("{} copies of a {} byte string are too large", count, s.len()));
+      if s.len().checked_mul(count).map_or(true, |length| length > isize::MAX as usize) {
+        return Err(too_large());
+      }
+      Sandbox::check_string(s.chars().count() * count)?;
+
+      let mut repeated = String::new();
+      repeated.try_reserve_exact(s.len() * count).map_err(|_| too_large())?;
+      for i in 0..count {
+        if i % 1024 == 1023 {
+          Sandbox::check_time()?;
+        }
+        repeated.push_str(s);
+      }
+      Ok(RuntimeType::String(repeated))
+    });
+
+    // Unicode code point of a one-char string.
+    Natives::define(env, "ord", &["char"], |args, _env| {
+      let s = NativeString::string("ord", &args[0])?;
+      let mut chars = s.chars();
+
+      match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(RuntimeType::Integer(c as i64)),
+        _ => Err(RuntimeError::InvalidArgument(String::from("ord"), format!("expected a single character, got \"{}\"", s)))
+      }
+    });
+
+    Natives::define(env, "chr", &["code"], |args, _env| {
+      let code = NativeString::index("chr", &args[0])?;
+
+      match u32::try_from(code).ok().and_then(char::from_u32) {
+        Some(c) => Ok(RuntimeType::String(c.to_string())),
+        None => Err(RuntimeError::InvalidArgument(String::from("chr"), format!("{} is not a valid code point", code)))
+      }
+    });
+
+    Natives::define(env, "list", &[], |_args, _env| {
+      Sandbox::allocate(1)?;
+      Ok(RuntimeType::List(Rc::new(RefCell::new(Vec::new()))))
+    });
+
+    // Appends in place and returns the list.
+    Natives::define(env, "push", &["list", "value"], |args, _env| {
+      match args[0].as_ref() {
+        RuntimeType::List(l) => {
+          Sandbox::allocate(1)?;
+          l.borrow_mut().push((*args[1]).clone());
+          GarbageCollector::track_list(l);
+          Ok(RuntimeType::List(l.clone()))
+        },
+        other => Err(RuntimeError::InvalidArgument(String::from("push"), format!("expected a list, got {}", other.type_name())))
+      }
+    });
+
+    Natives::define(env, "get", &["list", "index"], |args, _env| {
+      let index = NativeString::index("get", &args[1])?;
+
+      match args[0].as_ref() {
+        RuntimeType::List(l) => match l.borrow().get(index) {
+          Some(value) => Ok(value.clone()),
+          None => Err(RuntimeError::InvalidArgument(String::from("get"), format!("index {} is out of bounds for a list of length {}", index, l.borrow().len())))
+        },
+        other => Err(RuntimeError::InvalidArgument(String::from("get"), format!("expected a list, got {}", other.type_name())))
+      }
+    });
+  }
+
+  fn string<'a>(name: &str, value: &'a RuntimeType) -> Result<&'a str, RuntimeError> {
+    match value {
+      RuntimeType::String(s) => Ok(s),
+      _ => Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a string, got {}", value.type_name())))
+    }
+  }
+
+  fn index(name: &str, value: &RuntimeType) -> Result<usize, RuntimeError> {
+    match value.as_number() {
+      Some(n) if n >= 0. && n.fract() == 0. => Ok(n as usize),
+      Some(_) => Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a non-negative integer, got {}", value.to_string()))),
+      None => Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a number, got {}", value.type_name())))
+    }
+  }
+}
@@ -0,0 +1,67 @@
+use std::{cell::RefCell, env, rc::Rc};
+
+use crate::environment::Environment;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_type::RuntimeType;
+
+use super::Natives;
+
+thread_local! {
+  // Arguments given to the script after `--`, shared by the main script and the modules it imports.
+  static SCRIPT_ARGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+// Process-level natives: the script's arguments, environment variables and `exit`.
+pub struct NativeSystem;
+
+impl NativeSystem {
+  pub fn set_args(args: &[String]) {
+    SCRIPT_ARGS.with(|script_args| *script_args.borrow_mut() = args.to_vec());
+  }
+
+  pub fn define(env: &Rc<RefCell<Environment>>) {
+    let args: Vec<RuntimeType> = SCRIPT_ARGS.with(|script_args| script_args.borrow().iter().map(|a| RuntimeType::String(a.to_owned())).collect());
+    env.borrow_mut().define_builtin("args", Rc::new(RuntimeType::List(Rc::new(RefCell::new(args)))));
+
+    // The value, or nil if the variable is not set.
+    Natives::define(env, "getenv", &["name"], |args, _env| {
+      let name = NativeSystem::string("getenv", &args[0])?;
+
+      match env::var(name) {
+        Ok(value) => Ok(RuntimeType::String(value)),
+        Err(_) => Ok(RuntimeType::Nil())
+      }
+    });
+
+    Natives::define(env, "setenv", &["name", "value"], |args, _env| {
+      let name = NativeSystem::string("setenv", &args[0])?;
+      let value = NativeSystem::string("setenv", &args[1])?;
+
+      // `set_var` panics on these.
+      if name.is_empty() || name.contains('=') || name.contains('\0') {
+        return Err(RuntimeError::InvalidArgument(String::from("setenv"), format!("invalid variable name \"{}\"", name)));
+      }
+      if value.contains('\0') {
+        return Err(RuntimeError::InvalidArgument(String::from("setenv"), String::from("the value contains a NUL character")));
+      }
+
+      env::set_var(name, value);
+      Ok(RuntimeType::Nil())
+    });
+
+    // Unwinds like an error so the interpreter can finish its reports before exiting.
+    Natives::define(env, "exit", &["code"], |args, _env| {
+      match args[0].as_number() {
+        Some(n) if n.fract() == 0. && (0. ..=255.).contains(&n) => Err(RuntimeError::Exit(n as i32)),
+        _ => Err(RuntimeError::InvalidArgument(String::from("exit"), format!("expected an integer between 0 and 255, got {}", args[0].to_string())))
+      }
+    });
+  }
+
+  fn string<'a>(name: &str, value: &'a RuntimeType) -> Result<&'a str, RuntimeError> {
+    match value {
+      RuntimeType::String(s) => Ok(s),
+      _ => Err(RuntimeError::InvalidArgument(name.to_owned(), format!("expected a string, got {}", value.type_name())))
+    }
+  }
+}
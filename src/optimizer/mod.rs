@@ -0,0 +1,169 @@
+use std::{cell::{Cell, RefCell}, rc::Rc};
+
+use crate::environment::Environment;
+use crate::expression::{evaluator::ExprEvaluator, Expression};
+use crate::runtime::{runtime_number::RuntimeNumber, runtime_type::RuntimeType};
+use crate::sandbox::Sandbox;
+use crate::statement::Statement;
+use crate::token::Token;
+
+// Default for `--opt-level`.
+pub const DEFAULT_OPT_LEVEL: u8 = 2;
+
+thread_local! {
+  static LEVEL: Cell<u8> = const { Cell::new(DEFAULT_OPT_LEVEL) };
+}
+
+// Rewrites the AST before it runs. Level 1 folds expressions made only of literals into a single literal, level 2 also
+// drops `if` branches and loops whose condition is a constant. An expression that fails to evaluate is left as it is,
+// so the error is still raised when, and only if, the code runs.
+pub struct Optimizer;
+
+impl Optimizer {
+  pub fn set_level(level: u8) {
+    LEVEL.with(|l| l.set(level));
+  }
+
+  pub fn optimize(statements: Vec<Statement>) -> Vec<Statement> {
+    let level = LEVEL.with(|l| l.get());
+    if level == 0 {
+      return statements;
+    }
+
+    statements.into_iter().map(|s| Optimizer::statement(s, level)).collect()
+  }
+
+  fn statement(statement: Statement, level: u8) -> Statement {
+    match statement {
+      Statement::Print(e) => Statement::Print(Optimizer::boxed(*e)),
+      Statement::Expression(e) => Statement::Expression(Optimizer::boxed(*e)),
+      Statement::Var(name, e) => Statement::Var(name, Optimizer::boxed(*e)),
+      Statement::Return(e) => Statement::Return(Optimizer::boxed(*e)),
+      Statement::Function(name, args, body, captures) => Statement::Function(name, args, Box::new(Optimizer::statement(*body, level)), captures),
+      Statement::Block(statements) => Statement::Block(Box::new(statements.into_iter().map(|s| Optimizer::statement(s, level)).collect())),
+      Statement::If(condition, then_stmt, else_stmt) => {
+        let condition = Optimizer::expression(*condition);
+
+        match Optimizer::constant(&condition) {
+          Some(value) if level >= 2 => Optimizer::statement(if value.is_truthy() { *then_stmt } else { *else_stmt }, level),
+          _ => Statement::If(Box::new(condition), Box::new(Optimizer::statement(*then_stmt, level)), Box::new(Optimizer::statement(*else_stmt, level)))
+        }
+      },
+      Statement::While(condition, body) => {
+        let condition = Optimizer::expression(*condition);
+
+        match Optimizer::constant(&condition) {
+          Some(value) if level >= 2 && !value.is_truthy() => Statement::Empty(),
+          _ => Statement::While(Box::new(condition), Box::new(Optimizer::statement(*body, level)))
+        }
+      },
+      Statement::For(initializer, condition, increment, body) => {
+        let initializer = Optimizer::statement(*initializer, level);
+        let condition = Optimizer::expression(*condition);
+
+        // The initializer still runs, in its own scope like the loop's.
+        match Optimizer::constant(&condition) {
+          Some(value) if level >= 2 && !value.is_truthy() => Statement::Block(Box::new(vec![initializer])),
+          _ => Statement::For(Box::new(initializer), Box::new(condition), Optimizer::boxed(*increment), Box::new(Optimizer::statement(*body, level)))
+        }
+      },
+      Statement::Empty() | Statement::Import(_, _) | Statement::ImportFrom(_, _) | Statement::Native(_) => statement
+    }
+  }
+
+  fn boxed(expression: Expression) -> Box<Expression> {
+    Box::new(Optimizer::expression(expression))
+  }
+
+  fn expression(expression: Expression) -> Expression {
+    match expression {
+      Expression::Grouping(inner) => {
+        let inner = Optimizer::expression(*inner);
+        match Optimizer::constant(&inner) {
+          Some(_) => inner,
+          None => Expression::Grouping(Box::new(inner))
+        }
+      },
+      Expression::Unary(operator, right) => {
+        let right = Optimizer::expression(*right);
+        Optimizer::fold(Expression::Unary(operator, Box::new(right)))
+      },
+      Expression::Binary(left, operator, right) => {
+        let (left, right) = (Optimizer::expression(*left), Optimizer::expression(*right));
+        Optimizer::fold(Expression::Binary(Box::new(left), operator, Box::new(right)))
+      },
+      Expression::Logical(left, operator, right) => {
+        let (left, right) = (Optimizer::expression(*left), Optimizer::expression(*right));
+
+        match Optimizer::constant(&left) {
+          Some(value) => if ExprEvaluator::short_circuits(&operator, &value) { left } else { right },
+          None => Expression::Logical(Box::new(left), operator, Box::new(right))
+        }
+      },
+      Expression::Conditional(condition, question, then_expr, else_expr) => {
+        let condition = Optimizer::expression(*condition);
+        let (then_expr, else_expr) = (Optimizer::expression(*then_expr), Optimizer::expression(*else_expr));
+
+        match Optimizer::constant(&condition) {
+          Some(value) => if value.is_truthy() { then_expr } else { else_expr },
+          None => Expression::Conditional(Box::new(condition), question, Box::new(then_expr), Box::new(else_expr))
+        }
+      },
+      Expression::Interpolation(token, parts) => {
+        let parts = parts.into_iter().map(Optimizer::expression).collect();
+        Optimizer::fold(Expression::Interpolation(token, parts))
+      },
+      Expression::Assign(name, value, resolved) => Expression::Assign(name, Optimizer::boxed(*value), resolved),
+      Expression::Call(callee, arguments) => Expression::Call(Optimizer::boxed(*callee), arguments.into_iter().map(Optimizer::expression).collect()),
+      Expression::Get(object, name) => Expression::Get(Optimizer::boxed(*object), name),
+      Expression::Literal(_, _) | Expression::Identifier(_, _) | Expression::Update(_, _, _, _) | Expression::Nil() => expression
+    }
+  }
+
+  // Replaces an operation whose operands are all literals by its result.
+  fn fold(expression: Expression) -> Expression {
+    let operands_constant = match &expression {
+      Expression::Unary(_, right) => Optimizer::constant(right).is_some(),
+      Expression::Binary(left, _, right) => Optimizer::constant(left).is_some() && Optimizer::constant(right).is_some(),
+      Expression::Interpolation(_, parts) => parts.iter().all(|p| Optimizer::constant(p).is_some()),
+      _ => false
+    };
+
+    if !operands_constant {
+      return expression;
+    }
+
+    let line = expression.line().unwrap_or(0);
+    let env = Rc::new(RefCell::new(Environment::global()));
+    // Folding is the interpreter's own work, not the script's, so it isn't charged against the sandbox's limits.
+    let value = Sandbox::suspended(|| ExprEvaluator::evaluate(&expression, &env));
+
+    match value.ok().and_then(|value| Optimizer::literal(value, line)) {
+      Some(literal) => literal,
+      None => expression
+    }
+  }
+
+  // The value of a literal.
+  fn constant(expression: &Expression) -> Option<RuntimeType> {
+    match expression {
+      Expression::Literal(_token, value) => Some(value.clone()),
+      _ => None
+    }
+  }
+
+  fn literal(value: RuntimeType, line: usize) -> Option<Expression> {
+    let token = match &value {
+      RuntimeType::Boolean(true) => Token::t_true(line),
+      RuntimeType::Boolean(false) => Token::t_false(line),
+      RuntimeType::Nil() => Token::t_nil(line),
+      RuntimeType::Integer(i) => Token::t_number(i.to_string(), format!("{}.0", i), line),
+      RuntimeType::Number(n) => Token::t_number(RuntimeNumber::to_literal(*n), RuntimeNumber::to_literal(*n), line),
+      RuntimeType::BigInt(b) => Token::t_bigint(format!("{}n", b), b.to_string(), line),
+      RuntimeType::String(s) => Token::t_literal(format!("\"{}\"", s), s.clone(), line),
+      RuntimeType::Function(_) | RuntimeType::Module(_) | RuntimeType::List(_) => return None
+    };
+
+    Some(Expression::Literal(Rc::new(token), value))
+  }
+}
@@ -6,6 +6,7 @@ use std::rc::Rc;
 use parser_error::ParserError;
 
 use crate::expression::parser::ExprParser;
+use crate::resolver::Resolver;
 use crate::statement::parser::StmtParser;
 use crate::statement::Statement;
 use crate::token::Token;
@@ -19,6 +20,6 @@ impl Parser {
   }
 
   pub fn parse_statements(tokens: &Vec<Rc<Token>>) -> Result<Vec<Statement>, ParserError> {
-    StmtParser::parse(tokens)
+    StmtParser::parse(tokens).map(Resolver::resolve)
   }
 }
@@ -1,3 +1,9 @@
+#![allow(
+  clippy::inherent_to_string,
+  clippy::to_string_in_format_args,
+  clippy::useless_format
+)]
+
 use crate::token::TokenType;
 
 #[derive(Debug)]
@@ -1,3 +1,5 @@
+#![allow(clippy::ptr_arg)]
+
 use std::rc::Rc;
 
 use crate::token::{Token, TokenType};
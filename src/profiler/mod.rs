@@ -0,0 +1,177 @@
+use std::{cell::RefCell, collections::HashMap, fs, io, path::PathBuf, rc::Rc, time::{Duration, Instant}};
+
+use crate::environment::Environment;
+use crate::module::ModuleLoader;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_hook::RuntimeHook;
+use crate::statement::Statement;
+
+const SCRIPT_FRAME: &str = "<script>";
+
+// A function or line of a given file, so same-named functions and equal line numbers of imported files stay apart.
+// Natives have no file.
+type Location<T> = (Option<Rc<PathBuf>>, T);
+
+#[derive(Default)]
+struct FunctionStats {
+  calls: u64,
+  inclusive: Duration,
+  exclusive: Duration,
+  native: bool
+}
+
+struct ActiveCall {
+  function: Location<String>,
+  start: Instant,
+  // Time spent in callees, subtracted to get exclusive time.
+  children: Duration
+}
+
+struct ProfileData {
+  start: Instant,
+  end: Option<Instant>,
+  functions: HashMap<Location<String>, FunctionStats>,
+  lines: HashMap<Location<usize>, u64>,
+  stack: Vec<ActiveCall>,
+  // Exclusive time per call path, in the collapsed format used by flamegraph tools.
+  collapsed: HashMap<String, Duration>
+}
+
+// Records call counts, inclusive/exclusive time per function and line hit counts. Clones share the same data.
+#[derive(Clone)]
+pub struct Profiler {
+  data: Rc<RefCell<ProfileData>>
+}
+
+impl Profiler {
+  pub fn new() -> Self {
+    let data = ProfileData {
+      start: Instant::now(),
+      end: None,
+      functions: HashMap::new(),
+      lines: HashMap::new(),
+      stack: Vec::new(),
+      collapsed: HashMap::new()
+    };
+
+    Self { data: Rc::new(RefCell::new(data)) }
+  }
+
+  // Freezes the total run time used by the report and the collapsed stacks.
+  pub fn stop(&self) {
+    self.data.borrow_mut().end = Some(Instant::now());
+  }
+
+  // Prints the report to stderr, keeping stdout for the program.
+  pub fn report(&self) {
+    let data = self.data.borrow();
+    let total = data.total();
+    let script_exclusive = total.saturating_sub(data.collapsed.values().sum::<Duration>());
+
+    let mut functions: Vec<(String, &FunctionStats)> = data.functions.iter().map(|(f, stats)| (Profiler::label(f), stats)).collect();
+    functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(&b.0)));
+
+    eprintln!("Profile (total {:.3} ms)", Profiler::millis(total));
+    eprintln!("{:<24} {:>10} {:>16} {:>16}", "Function", "Calls", "Inclusive (ms)", "Exclusive (ms)");
+    eprintln!("{:<24} {:>10} {:>16.3} {:>16.3}", SCRIPT_FRAME, 1, Profiler::millis(total), Profiler::millis(script_exclusive));
+    for (name, stats) in functions {
+      let label = if stats.native { format!("{} (native)", name) } else { name };
+      eprintln!("{:<24} {:>10} {:>16.3} {:>16.3}", label, stats.calls, Profiler::millis(stats.inclusive), Profiler::millis(stats.exclusive));
+    }
+
+    let mut lines: Vec<(&Location<usize>, &u64)> = data.lines.iter().collect();
+    lines.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+    eprintln!();
+    eprintln!("{:<8} {:>10}", "Line", "Hits");
+    for (line, hits) in lines {
+      eprintln!("{:<8} {:>10}", Profiler::label(line), hits);
+    }
+  }
+
+  // Writes one `frame;frame;frame microseconds` line per call path.
+  pub fn write_collapsed(&self, path: &str) -> io::Result<()> {
+    let data = self.data.borrow();
+    let total = data.total();
+    let script_exclusive = total.saturating_sub(data.collapsed.values().sum::<Duration>());
+
+    let mut stacks: Vec<(&String, &Duration)> = data.collapsed.iter().collect();
+    stacks.sort();
+
+    let mut output = format!("{} {}\n", SCRIPT_FRAME, script_exclusive.as_micros());
+    for (stack, time) in stacks {
+      output.push_str(&format!("{} {}\n", stack, time.as_micros()));
+    }
+
+    fs::write(path, output)
+  }
+
+  // Functions and lines of imported files are prefixed with the file name.
+  fn label<T: ToString>(location: &Location<T>) -> String {
+    match &location.0 {
+      Some(file) if !ModuleLoader::is_main_file(Some(file)) => format!("{}:{}", ModuleLoader::display(file), location.1.to_string()),
+      _ => location.1.to_string()
+    }
+  }
+
+  fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.
+  }
+}
+
+impl ProfileData {
+  fn total(&self) -> Duration {
+    match self.end {
+      Some(end) => end - self.start,
+      None => self.start.elapsed()
+    }
+  }
+}
+
+impl RuntimeHook for Profiler {
+  fn before_statement(&mut self, _statement: &Statement, line: usize, _env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    *self.data.borrow_mut().lines.entry((ModuleLoader::current_file(), line)).or_insert(0) += 1;
+    Ok(())
+  }
+
+  fn on_call(&mut self, fun_name: &str, native: bool, _call_line: usize) {
+    let mut data = self.data.borrow_mut();
+
+    // The callee's file is already the current one.
+    let function = (if native { None } else { ModuleLoader::current_file() }, fun_name.to_owned());
+    let stats = data.functions.entry(function.clone()).or_default();
+    stats.calls += 1;
+    stats.native = native;
+
+    data.stack.push(ActiveCall { function, start: Instant::now(), children: Duration::ZERO });
+  }
+
+  fn on_return(&mut self, _fun_name: &str) {
+    let mut data = self.data.borrow_mut();
+
+    let call = match data.stack.pop() {
+      Some(call) => call,
+      None => return
+    };
+    let elapsed = call.start.elapsed();
+    let exclusive = elapsed.saturating_sub(call.children);
+
+    // Recursive calls would otherwise count the same time several times.
+    let recursive = data.stack.iter().any(|c| c.function == call.function);
+    let stats = data.functions.entry(call.function.clone()).or_default();
+    if !recursive {
+      stats.inclusive += elapsed;
+    }
+    stats.exclusive += exclusive;
+
+    let mut path: Vec<String> = vec![SCRIPT_FRAME.to_owned()];
+    path.extend(data.stack.iter().map(|c| Profiler::label(&c.function)));
+    path.push(Profiler::label(&call.function));
+    let path = path.join(";");
+    *data.collapsed.entry(path).or_default() += exclusive;
+
+    if let Some(parent) = data.stack.last_mut() {
+      parent.children += elapsed;
+    }
+  }
+}
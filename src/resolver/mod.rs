@@ -0,0 +1,171 @@
+use std::rc::Rc;
+
+use crate::expression::{Expression, Resolved};
+use crate::statement::{Captures, Statement};
+use crate::token::Token;
+
+// Finds the slot each local variable lives in, so it is read without looking its name up. The scopes follow the ones
+// the interpreter creates: one per block, one for a function's parameters (its body block is another) and one for a
+// `for` loop's initializer. Top-level code has no scope, so globals keep being looked up by name, as are names used
+// before they are declared. Functions declared in a block are declared as the block starts, so they can call each
+// other in any order.
+//
+// A function doesn't keep the scopes it was declared in. The variables it uses from enclosing functions and blocks are
+// captured when it is declared and added to its parameters' scope, after the parameters, where its body finds them.
+pub struct Resolver {
+  // Names declared so far in each enclosing scope, innermost last. A name's index is its slot.
+  scopes: Vec<Vec<String>>,
+  // The functions being resolved, innermost last.
+  functions: Vec<FunctionScope>
+}
+
+struct FunctionScope {
+  // Index in `scopes` of the function's parameters' scope.
+  scope: usize,
+  variables: Vec<(Rc<Token>, usize, usize)>
+}
+
+impl Resolver {
+  pub fn resolve(statements: Vec<Statement>) -> Vec<Statement> {
+    let mut resolver = Resolver { scopes: Vec::new(), functions: Vec::new() };
+    statements.into_iter().map(|s| resolver.statement(s)).collect()
+  }
+
+  fn statement(&mut self, statement: Statement) -> Statement {
+    match statement {
+      Statement::Print(e) => Statement::Print(self.boxed(*e)),
+      Statement::Expression(e) => Statement::Expression(self.boxed(*e)),
+      Statement::Var(name, e) => {
+        // The initializer sees the enclosing variable of the same name, if any.
+        let e = self.boxed(*e);
+        self.declare(&name.lexeme);
+        Statement::Var(name, e)
+      },
+      Statement::Return(e) => Statement::Return(self.boxed(*e)),
+      Statement::Function(name, args, body, _captures) => {
+        // Declared first so the body can call itself.
+        self.declare(&name.lexeme);
+        // From the declaring scope up to the enclosing function's parameters, then one more to its top-level scope.
+        let top_level = self.scopes.len() - self.functions.last().map_or(0, |f| f.scope);
+
+        self.scopes.push(Vec::new());
+        self.functions.push(FunctionScope { scope: self.scopes.len() - 1, variables: Vec::new() });
+        for arg in &args {
+          self.declare(&arg.lexeme);
+        }
+        let body = self.statement(*body);
+        let function = self.functions.pop().unwrap();
+        self.scopes.pop();
+
+        Statement::Function(name, args, Box::new(body), Captures { variables: function.variables, top_level })
+      },
+      Statement::Block(statements) => {
+        self.scopes.push(Vec::new());
+        for statement in statements.iter() {
+          if let Statement::Function(name, _args, _body, _captures) = statement {
+            self.declare(&name.lexeme);
+          }
+        }
+        let statements = statements.into_iter().map(|s| self.statement(s)).collect();
+        self.scopes.pop();
+
+        Statement::Block(Box::new(statements))
+      },
+      Statement::If(condition, then_stmt, else_stmt) => {
+        Statement::If(self.boxed(*condition), Box::new(self.statement(*then_stmt)), Box::new(self.statement(*else_stmt)))
+      },
+      Statement::While(condition, body) => Statement::While(self.boxed(*condition), Box::new(self.statement(*body))),
+      Statement::For(initializer, condition, increment, body) => {
+        // Every iteration copies the loop's scope, slots included.
+        self.scopes.push(Vec::new());
+        let initializer = self.statement(*initializer);
+        let (condition, increment) = (self.boxed(*condition), self.boxed(*increment));
+        let body = self.statement(*body);
+        self.scopes.pop();
+
+        Statement::For(Box::new(initializer), condition, increment, Box::new(body))
+      },
+      Statement::Import(path, alias) => {
+        self.declare(&alias.lexeme);
+        Statement::Import(path, alias)
+      },
+      Statement::ImportFrom(path, names) => {
+        for name in &names {
+          self.declare(&name.lexeme);
+        }
+        Statement::ImportFrom(path, names)
+      },
+      Statement::Empty() | Statement::Native(_) => statement
+    }
+  }
+
+  fn boxed(&mut self, expression: Expression) -> Box<Expression> {
+    Box::new(self.expression(expression))
+  }
+
+  fn expression(&mut self, expression: Expression) -> Expression {
+    match expression {
+      Expression::Identifier(name, _resolved) => {
+        let resolved = self.lookup(&name);
+        Expression::Identifier(name, resolved)
+      },
+      Expression::Assign(name, value, _resolved) => {
+        let value = self.boxed(*value);
+        let resolved = self.lookup(&name);
+        Expression::Assign(name, value, resolved)
+      },
+      Expression::Update(name, operator, postfix, _resolved) => {
+        let resolved = self.lookup(&name);
+        Expression::Update(name, operator, postfix, resolved)
+      },
+      Expression::Unary(operator, right) => Expression::Unary(operator, self.boxed(*right)),
+      Expression::Binary(left, operator, right) => {
+        let left = self.boxed(*left);
+        Expression::Binary(left, operator, self.boxed(*right))
+      },
+      Expression::Grouping(inner) => Expression::Grouping(self.boxed(*inner)),
+      Expression::Logical(left, operator, right) => {
+        let left = self.boxed(*left);
+        Expression::Logical(left, operator, self.boxed(*right))
+      },
+      Expression::Conditional(condition, question, then_expr, else_expr) => {
+        let condition = self.boxed(*condition);
+        let then_expr = self.boxed(*then_expr);
+        Expression::Conditional(condition, question, then_expr, self.boxed(*else_expr))
+      },
+      Expression::Call(callee, arguments) => {
+        let callee = self.boxed(*callee);
+        Expression::Call(callee, arguments.into_iter().map(|a| self.expression(a)).collect())
+      },
+      Expression::Get(object, name) => Expression::Get(self.boxed(*object), name),
+      Expression::Interpolation(token, parts) => Expression::Interpolation(token, parts.into_iter().map(|p| self.expression(p)).collect()),
+      Expression::Literal(_, _) | Expression::Nil() => expression
+    }
+  }
+
+  // Declaring a name again in the same scope reuses its slot, like `Environment::define` does.
+  fn declare(&mut self, name: &str) {
+    if let Some(scope) = self.scopes.last_mut() {
+      if !scope.iter().any(|n| n == name) {
+        scope.push(name.to_owned());
+      }
+    }
+  }
+
+  fn lookup(&mut self, name: &Rc<Token>) -> Resolved {
+    let (mut index, mut slot) = self.scopes.iter().enumerate().rev()
+      .find_map(|(index, scope)| scope.iter().position(|n| *n == name.lexeme).map(|slot| (index, slot)))?;
+
+    // Every function between the use and the declaration captures the variable, the outermost first, so each inner one
+    // captures it from the function around it.
+    let crossed = self.functions.iter().position(|f| f.scope > index).unwrap_or(self.functions.len());
+    for function in self.functions[crossed..].iter_mut() {
+      function.variables.push((name.clone(), function.scope - 1 - index, slot));
+      self.scopes[function.scope].push(name.lexeme.to_string());
+      index = function.scope;
+      slot = self.scopes[index].len() - 1;
+    }
+
+    Some((self.scopes.len() - 1 - index, slot))
+  }
+}
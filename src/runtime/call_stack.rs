@@ -0,0 +1,98 @@
+use std::{cell::{Cell, RefCell}, path::PathBuf, rc::Rc};
+
+use crate::environment::Environment;
+
+use super::runtime_error::RuntimeError;
+
+#[derive(Clone)]
+pub struct CallFrame {
+  pub fun_name: String,
+  // Line of the call expression in the caller.
+  pub call_line: usize,
+  // Scope the call expression was evaluated in, and its file.
+  pub caller_env: Rc<RefCell<Environment>>,
+  pub caller_file: Option<Rc<PathBuf>>,
+  // Functions that ran in this frame before `fun_name` and ended with `return fun_name(...)`.
+  pub tail_calls: usize
+}
+
+// Default for `--max-depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// Frames shown at each end of a stack overflow's backtrace.
+const TRACE_EDGE: usize = 10;
+
+thread_local! {
+  static FRAMES: RefCell<Vec<CallFrame>> = const { RefCell::new(Vec::new()) };
+  static MAX_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_DEPTH) };
+}
+
+pub struct CallStack;
+
+impl CallStack {
+  pub fn push(frame: CallFrame) {
+    FRAMES.with(|frames| frames.borrow_mut().push(frame));
+  }
+
+  // The innermost frame's function made a tail call to `fun_name`, which now runs in its place.
+  pub fn tail_call(fun_name: String) {
+    FRAMES.with(|frames| {
+      if let Some(frame) = frames.borrow_mut().last_mut() {
+        frame.fun_name = fun_name;
+        frame.tail_calls += 1;
+      }
+    });
+  }
+
+  pub fn pop() {
+    FRAMES.with(|frames| frames.borrow_mut().pop());
+  }
+
+  // Number of active function calls. Top-level code runs at depth 0.
+  pub fn depth() -> usize {
+    FRAMES.with(|frames| frames.borrow().len())
+  }
+
+  pub fn set_max_depth(max_depth: usize) {
+    MAX_DEPTH.with(|max| max.set(max_depth));
+  }
+
+  // Fails when one more call would go over `--max-depth`. `line` is the line of that call.
+  pub fn check_depth(line: usize) -> Result<(), RuntimeError> {
+    if CallStack::depth() < MAX_DEPTH.with(|max| max.get()) {
+      return Ok(());
+    }
+
+    let mut trace = CallStack::backtrace(line);
+    if trace.len() > TRACE_EDGE * 2 {
+      let hidden = trace.len() - TRACE_EDGE * 2;
+      trace.splice(TRACE_EDGE..trace.len() - TRACE_EDGE, [format!("... {} more frames", hidden)]);
+    }
+
+    Err(RuntimeError::StackOverflow(trace))
+  }
+
+  // One line per frame, innermost first, ending with the top-level script. `line` is the current line of the
+  // innermost frame.
+  pub fn backtrace(line: usize) -> Vec<String> {
+    let frames = CallStack::frames();
+    let mut current_line = line;
+    let mut trace = Vec::new();
+
+    for (i, frame) in frames.iter().enumerate() {
+      match frame.tail_calls {
+        0 => trace.push(format!("#{} {} at line {}", i, frame.fun_name, current_line)),
+        n => trace.push(format!("#{} {} at line {} (after {} tail calls)", i, frame.fun_name, current_line, n))
+      }
+      current_line = frame.call_line;
+    }
+
+    trace.push(format!("#{} <script> at line {}", frames.len(), current_line));
+    trace
+  }
+
+  // Active frames, innermost first.
+  pub fn frames() -> Vec<CallFrame> {
+    FRAMES.with(|frames| frames.borrow().iter().rev().cloned().collect())
+  }
+}
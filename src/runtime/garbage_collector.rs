@@ -0,0 +1,258 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::{Rc, Weak}};
+
+use crate::environment::{Binding, Environment};
+use crate::module::ModuleLoader;
+
+use super::{call_stack::CallStack, runtime_function::RuntimeFunction, runtime_module::RuntimeModule, runtime_type::RuntimeType};
+
+// Collections start once this many objects were tracked since the previous one.
+const INITIAL_THRESHOLD: usize = 1000;
+
+struct CollectorState {
+  // Top-level scopes and variables captured by a function, and lists that had a value pushed: the only objects that
+  // can end up in a cycle, and the ones a collection sweeps.
+  envs: HashMap<usize, Weak<RefCell<Environment>>>,
+  bindings: HashMap<usize, Weak<RefCell<Rc<RuntimeType>>>>,
+  lists: HashMap<usize, Weak<RefCell<Vec<RuntimeType>>>>,
+  allocations: usize,
+  threshold: usize,
+  collections: usize,
+  freed: usize
+}
+
+thread_local! {
+  static STATE: RefCell<CollectorState> = RefCell::new(CollectorState {
+    envs: HashMap::new(),
+    bindings: HashMap::new(),
+    lists: HashMap::new(),
+    allocations: 0,
+    threshold: INITIAL_THRESHOLD,
+    collections: 0,
+    freed: 0
+  });
+
+  // What the interpreter holds outside any variable while Lox code runs, innermost last.
+  static ROOTS: RefCell<Vec<Node>> = const { RefCell::new(Vec::new()) };
+}
+
+// One object of the traced heap, keyed by its address.
+#[derive(Clone)]
+enum Node {
+  Env(Rc<RefCell<Environment>>),
+  Binding(Binding),
+  Value(Rc<RuntimeType>),
+  Function(Rc<RuntimeFunction>),
+  List(Rc<RefCell<Vec<RuntimeType>>>),
+  Module(Rc<RuntimeModule>)
+}
+
+impl Node {
+  fn id(&self) -> usize {
+    match self {
+      Node::Env(e) => Rc::as_ptr(e) as *const () as usize,
+      Node::Binding(b) => Rc::as_ptr(b) as *const () as usize,
+      Node::Value(v) => Rc::as_ptr(v) as *const () as usize,
+      Node::Function(f) => Rc::as_ptr(f) as *const () as usize,
+      Node::List(l) => Rc::as_ptr(l) as *const () as usize,
+      Node::Module(m) => Rc::as_ptr(m) as *const () as usize
+    }
+  }
+
+  // Every object this one references. None when it is borrowed right now.
+  fn children(&self) -> Option<Vec<Node>> {
+    let mut children = Vec::new();
+
+    match self {
+      Node::Env(e) => {
+        let env = e.try_borrow().ok()?;
+        if let Some(enclosing) = env.enclosing() {
+          children.push(Node::Env(enclosing));
+        }
+        for binding in env.slots() {
+          children.push(Node::Binding(binding));
+        }
+      },
+      Node::Binding(b) => children.push(Node::Value(b.try_borrow().ok()?.clone())),
+      Node::Value(v) => Node::value_children(v, &mut children),
+      Node::Function(f) => {
+        children.push(Node::Env(f.get_top_level()));
+        for binding in f.get_captures() {
+          children.push(Node::Binding(binding));
+        }
+      },
+      Node::List(l) => {
+        for value in l.try_borrow().ok()?.iter() {
+          Node::value_children(value, &mut children);
+        }
+      },
+      Node::Module(m) => children.push(Node::Env(m.get_env()))
+    }
+
+    Some(children)
+  }
+
+  fn value_children(value: &RuntimeType, children: &mut Vec<Node>) {
+    match value {
+      RuntimeType::Function(f) => children.push(Node::Function(f.clone())),
+      RuntimeType::List(l) => children.push(Node::List(l.clone())),
+      RuntimeType::Module(m) => children.push(Node::Module(m.clone())),
+      _ => {}
+    }
+  }
+}
+
+// Keeps what was rooted with it alive through collections until it is dropped.
+pub struct Root {
+  depth: usize
+}
+
+impl Drop for Root {
+  fn drop(&mut self) {
+    ROOTS.with(|roots| roots.borrow_mut().truncate(self.depth));
+  }
+}
+
+// Reference counting frees everything but cycles, like a function stored in a variable it captured. This collector
+// finds them by mark and sweep. The roots are the global scope, the scopes of the calls on the call stack, the cached
+// modules, the scope of the statement about to run, and what the interpreter holds in between, like a call's callee and
+// the arguments evaluated so far (see `root_env` and `root_value`). Everything they reach is marked; the objects that can
+// form a cycle (top-level scopes and captured variables that functions hold, and lists) are tracked, and the unmarked
+// ones are emptied, which breaks their cycles and lets `Rc` free them. Values stay reference counted, so everything
+// else is still freed as soon as its last handle goes. Collections only run before a statement, where every value in
+// use is reachable from a root.
+pub struct GarbageCollector;
+
+impl GarbageCollector {
+  pub fn track_env(env: &Rc<RefCell<Environment>>) {
+    let id = Rc::as_ptr(env) as *const () as usize;
+    STATE.with(|state| state.borrow_mut().envs.insert(id, Rc::downgrade(env)));
+    GarbageCollector::allocated();
+  }
+
+  pub fn track_binding(binding: &Binding) {
+    let id = Rc::as_ptr(binding) as *const () as usize;
+    STATE.with(|state| state.borrow_mut().bindings.insert(id, Rc::downgrade(binding)));
+    GarbageCollector::allocated();
+  }
+
+  pub fn track_list(list: &Rc<RefCell<Vec<RuntimeType>>>) {
+    let id = Rc::as_ptr(list) as *const () as usize;
+    STATE.with(|state| state.borrow_mut().lists.insert(id, Rc::downgrade(list)));
+    GarbageCollector::allocated();
+  }
+
+  fn allocated() {
+    STATE.with(|state| state.borrow_mut().allocations += 1);
+  }
+
+  // For a scope Lox code can't reach while it runs, like the global one or that of the code importing a module.
+  pub fn root_env(env: &Rc<RefCell<Environment>>) -> Root {
+    GarbageCollector::root(vec![Node::Env(env.clone())])
+  }
+
+  // For a value only the interpreter holds, like an argument waiting for the others to be evaluated.
+  pub fn root_value(value: &RuntimeType) -> Root {
+    let mut nodes = Vec::new();
+    Node::value_children(value, &mut nodes);
+    GarbageCollector::root(nodes)
+  }
+
+  fn root(nodes: Vec<Node>) -> Root {
+    ROOTS.with(|roots| {
+      let mut roots = roots.borrow_mut();
+      let depth = roots.len();
+      roots.extend(nodes);
+      Root { depth }
+    })
+  }
+
+  // Called before every statement, with the scope it runs in. Collects once enough was tracked since the last time.
+  pub fn safepoint(env: &Rc<RefCell<Environment>>) {
+    let due = STATE.with(|state| {
+      let state = state.borrow();
+      state.allocations >= state.threshold
+    });
+
+    if due {
+      GarbageCollector::collect_from(Some(env));
+    }
+  }
+
+  // Outside of a statement: only the roots count.
+  pub fn collect() {
+    GarbageCollector::collect_from(None);
+  }
+
+  fn collect_from(current: Option<&Rc<RefCell<Environment>>>) {
+    let tracked = STATE.with(|state| {
+      let mut state = state.borrow_mut();
+      state.envs.retain(|_id, env| env.strong_count() > 0);
+      state.bindings.retain(|_id, binding| binding.strong_count() > 0);
+      state.lists.retain(|_id, list| list.strong_count() > 0);
+      state.allocations = 0;
+
+      let envs = state.envs.values().filter_map(|e| e.upgrade()).map(Node::Env);
+      let bindings = state.bindings.values().filter_map(|b| b.upgrade()).map(Node::Binding);
+      let lists = state.lists.values().filter_map(|l| l.upgrade()).map(Node::List);
+      envs.chain(bindings).chain(lists).collect::<Vec<Node>>()
+    });
+
+    let mut pending: Vec<Node> = ROOTS.with(|roots| roots.borrow().clone());
+    pending.extend(current.cloned().map(Node::Env));
+    pending.extend(CallStack::frames().into_iter().map(|frame| Node::Env(frame.caller_env)));
+    pending.extend(ModuleLoader::modules().into_iter().map(Node::Module));
+
+    // Mark everything the roots reach. Objects looked at, so the next collection waits until the program tracked
+    // about as many again.
+    let mut marked: HashSet<usize> = HashSet::new();
+    let mut work = 0;
+
+    while let Some(node) = pending.pop() {
+      if !marked.insert(node.id()) {
+        continue;
+      }
+
+      // A borrowed object is in use; collecting now could free something the interpreter is about to touch.
+      let children = match node.children() {
+        Some(children) => children,
+        None => return
+      };
+
+      work += children.len() + 1;
+      pending.extend(children.into_iter().filter(|child| !marked.contains(&child.id())));
+    }
+
+    // Sweep: emptying the unmarked objects breaks their cycles; dropping `tracked` then frees them.
+    let mut freed = 0;
+    for node in &tracked {
+      if marked.contains(&node.id()) {
+        continue;
+      }
+
+      match node {
+        Node::Env(e) => e.borrow_mut().clear(),
+        Node::Binding(b) => *b.borrow_mut() = Rc::new(RuntimeType::Nil()),
+        Node::List(l) => l.borrow_mut().clear(),
+        Node::Value(_) | Node::Function(_) | Node::Module(_) => continue
+      }
+      freed += 1;
+    }
+
+    drop(tracked);
+
+    STATE.with(|state| {
+      let mut state = state.borrow_mut();
+      state.collections += 1;
+      state.freed += freed;
+      state.threshold = INITIAL_THRESHOLD.max(work);
+    });
+  }
+
+  // Printed by `run --gc-stats`.
+  pub fn stats() -> String {
+    STATE.with(|state| {
+      let state = state.borrow();
+      format!("GC: {} collections, {} objects freed", state.collections, state.freed)
+    })
+  }
+}
@@ -1,3 +1,9 @@
+pub mod call_stack;
+pub mod garbage_collector;
+pub mod runtime_bigint;
 pub mod runtime_function;
+pub mod runtime_hook;
+pub mod runtime_module;
+pub mod runtime_number;
 pub mod runtime_type;
 pub mod runtime_error;
\ No newline at end of file
@@ -0,0 +1,314 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::sandbox::Sandbox;
+
+use super::runtime_error::RuntimeError;
+
+// Largest result `pow` computes, in 32-bit limbs: about 39,000 decimal digits.
+pub const MAX_POW_LIMBS: u64 = 4096;
+
+// Arbitrary-precision integer: a sign and a magnitude in base 2^32, least significant limb first, without leading zero
+// limbs. Zero has an empty magnitude and is never negative.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuntimeBigInt {
+  negative: bool,
+  magnitude: Vec<u32>
+}
+
+impl RuntimeBigInt {
+  pub fn zero() -> Self {
+    Self { negative: false, magnitude: Vec::new() }
+  }
+
+  pub fn from_i64(value: i64) -> Self {
+    let abs = value.unsigned_abs();
+    RuntimeBigInt::new(value < 0, vec![abs as u32, (abs >> 32) as u32])
+  }
+
+  // Exact conversion of an integral float. None for fractions and non-finite values.
+  pub fn from_f64(value: f64) -> Option<Self> {
+    if !value.is_finite() || value.fract() != 0. {
+      return None;
+    }
+
+    let mut magnitude: Vec<u32> = Vec::new();
+    let mut rest = value.abs();
+    while rest >= 1. {
+      magnitude.push((rest % 4294967296.) as u32);
+      rest = (rest / 4294967296.).floor();
+    }
+
+    Some(RuntimeBigInt::new(value < 0., magnitude))
+  }
+
+  // Decimal digits with an optional leading '-'. Underscores are ignored.
+  pub fn parse(text: &str) -> Option<Self> {
+    let (negative, digits) = match text.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, text)
+    };
+
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+
+    let mut magnitude: Vec<u32> = Vec::new();
+    for digit in digits.bytes() {
+      RuntimeBigInt::mul_small_add(&mut magnitude, 10, (digit - b'0') as u32);
+    }
+
+    Some(RuntimeBigInt::new(negative, magnitude))
+  }
+
+  fn decimal(&self) -> String {
+    if self.magnitude.is_empty() {
+      return String::from("0");
+    }
+
+    // Nine decimal digits at a time.
+    let mut chunks: Vec<u32> = Vec::new();
+    let mut rest = self.magnitude.clone();
+    while !rest.is_empty() {
+      chunks.push(RuntimeBigInt::div_small(&mut rest, 1_000_000_000));
+    }
+
+    let mut result = String::from(if self.negative { "-" } else { "" });
+    result.push_str(&chunks.pop().unwrap_or(0).to_string());
+    for chunk in chunks.iter().rev() {
+      result.push_str(&format!("{:09}", chunk));
+    }
+    result
+  }
+
+  pub fn to_f64(&self) -> f64 {
+    let value = self.magnitude.iter().rev().fold(0., |acc, limb| acc * 4294967296. + *limb as f64);
+    if self.negative { -value } else { value }
+  }
+
+  // None if the value does not fit.
+  pub fn to_i64(&self) -> Option<i64> {
+    if self.magnitude.len() > 2 {
+      return None;
+    }
+
+    let abs = self.magnitude.iter().rev().fold(0u64, |acc, limb| (acc << 32) | *limb as u64);
+    if self.negative {
+      0i64.checked_sub_unsigned(abs)
+    } else {
+      i64::try_from(abs).ok()
+    }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.magnitude.is_empty()
+  }
+
+  pub fn negate(&self) -> Self {
+    RuntimeBigInt::new(!self.negative, self.magnitude.clone())
+  }
+
+  pub fn add(&self, other: &RuntimeBigInt) -> Self {
+    if self.negative == other.negative {
+      return RuntimeBigInt::new(self.negative, RuntimeBigInt::add_magnitudes(&self.magnitude, &other.magnitude));
+    }
+
+    match RuntimeBigInt::cmp_magnitudes(&self.magnitude, &other.magnitude) {
+      Ordering::Less => RuntimeBigInt::new(other.negative, RuntimeBigInt::sub_magnitudes(&other.magnitude, &self.magnitude)),
+      _ => RuntimeBigInt::new(self.negative, RuntimeBigInt::sub_magnitudes(&self.magnitude, &other.magnitude))
+    }
+  }
+
+  pub fn sub(&self, other: &RuntimeBigInt) -> Self {
+    self.add(&other.negate())
+  }
+
+  // Fails only when the sandbox's time runs out: a product of the largest bigints it allows takes seconds.
+  pub fn mul(&self, other: &RuntimeBigInt) -> Result<Self, RuntimeError> {
+    let mut result = vec![0u32; self.magnitude.len() + other.magnitude.len()];
+
+    for (i, a) in self.magnitude.iter().enumerate() {
+      if i % 64 == 63 {
+        Sandbox::check_time()?;
+      }
+
+      let mut carry = 0u64;
+      for (j, b) in other.magnitude.iter().enumerate() {
+        let product = *a as u64 * *b as u64 + result[i + j] as u64 + carry;
+        result[i + j] = product as u32;
+        carry = product >> 32;
+      }
+      result[i + other.magnitude.len()] = carry as u32;
+    }
+
+    Ok(RuntimeBigInt::new(self.negative != other.negative, result))
+  }
+
+  // Floored division, like for integers: the remainder has the sign of the divisor. None when dividing by zero.
+  pub fn div_rem(&self, other: &RuntimeBigInt) -> Option<(RuntimeBigInt, RuntimeBigInt)> {
+    if other.is_zero() {
+      return None;
+    }
+
+    let (quotient, remainder) = RuntimeBigInt::div_rem_magnitudes(&self.magnitude, &other.magnitude);
+    let quotient = RuntimeBigInt::new(self.negative != other.negative, quotient);
+    let remainder = RuntimeBigInt::new(self.negative, remainder);
+
+    if !remainder.is_zero() && remainder.negative != other.negative {
+      return Some((quotient.sub(&RuntimeBigInt::from_i64(1)), remainder.add(other)));
+    }
+
+    Some((quotient, remainder))
+  }
+
+  // About how many bits `pow` returns: log2(|base|) * exponent. 0 and 1 stay small whatever the exponent.
+  pub fn pow_bits(&self, exponent: u64) -> f64 {
+    let log2 = self.to_f64().abs().log2().min(self.bits() as f64);
+    if log2 > 0. { log2 * exponent as f64 } else { 0. }
+  }
+
+  // Callers check `pow_bits` against `MAX_POW_LIMBS` first. Fails only when the sandbox's time runs out.
+  pub fn pow(&self, mut exponent: u64) -> Result<Self, RuntimeError> {
+    let mut result = RuntimeBigInt::from_i64(1);
+    let mut base = self.clone();
+
+    while exponent > 0 {
+      if exponent & 1 == 1 {
+        result = result.mul(&base)?;
+      }
+      exponent >>= 1;
+      if exponent > 0 {
+        base = base.mul(&base)?;
+      }
+    }
+
+    Ok(result)
+  }
+
+  pub fn bits(&self) -> u64 {
+    match self.magnitude.last() {
+      Some(top) => (self.magnitude.len() as u64 - 1) * 32 + (32 - top.leading_zeros()) as u64,
+      None => 0
+    }
+  }
+
+  fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+    while magnitude.last() == Some(&0) {
+      magnitude.pop();
+    }
+
+    Self { negative: negative && !magnitude.is_empty(), magnitude }
+  }
+
+  fn cmp_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+  }
+
+  fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+
+    for i in 0..a.len().max(b.len()) {
+      let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+      result.push(sum as u32);
+      carry = sum >> 32;
+    }
+    result.push(carry as u32);
+
+    result
+  }
+
+  // Requires a >= b.
+  fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, limb) in a.iter().enumerate() {
+      let mut difference = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+      borrow = 0;
+      if difference < 0 {
+        difference += 1 << 32;
+        borrow = 1;
+      }
+      result.push(difference as u32);
+    }
+
+    result
+  }
+
+  // Binary long division, one bit of the dividend at a time.
+  fn div_rem_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if b.len() == 1 {
+      let mut quotient = a.to_vec();
+      let remainder = RuntimeBigInt::div_small(&mut quotient, b[0]);
+      return (quotient, vec![remainder]);
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+
+    for bit in (0..a.len() * 32).rev() {
+      RuntimeBigInt::mul_small_add(&mut remainder, 2, (a[bit / 32] >> (bit % 32)) & 1);
+
+      if RuntimeBigInt::cmp_magnitudes(&remainder, b) != Ordering::Less {
+        remainder = RuntimeBigInt::sub_magnitudes(&remainder, b);
+        while remainder.last() == Some(&0) {
+          remainder.pop();
+        }
+        quotient[bit / 32] |= 1 << (bit % 32);
+      }
+    }
+
+    (quotient, remainder)
+  }
+
+  // magnitude = magnitude * factor + addend
+  fn mul_small_add(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in magnitude.iter_mut() {
+      let value = *limb as u64 * factor as u64 + carry;
+      *limb = value as u32;
+      carry = value >> 32;
+    }
+    if carry > 0 {
+      magnitude.push(carry as u32);
+    }
+  }
+
+  // Divides in place and returns the remainder.
+  fn div_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for limb in magnitude.iter_mut().rev() {
+      let value = (remainder << 32) | *limb as u64;
+      *limb = (value / divisor as u64) as u32;
+      remainder = value % divisor as u64;
+    }
+    while magnitude.last() == Some(&0) {
+      magnitude.pop();
+    }
+    remainder as u32
+  }
+}
+
+impl PartialOrd for RuntimeBigInt {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for RuntimeBigInt {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self.negative, other.negative) {
+      (false, true) => Ordering::Greater,
+      (true, false) => Ordering::Less,
+      (false, false) => RuntimeBigInt::cmp_magnitudes(&self.magnitude, &other.magnitude),
+      (true, true) => RuntimeBigInt::cmp_magnitudes(&other.magnitude, &self.magnitude)
+    }
+  }
+}
+
+impl fmt::Display for RuntimeBigInt {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.decimal())
+  }
+}
@@ -1,4 +1,9 @@
-use super::runtime_type::RuntimeType;
+#![allow(
+  clippy::inherent_to_string,
+  clippy::to_string_in_format_args
+)]
+
+use super::{runtime_function::TailCall, runtime_type::RuntimeType};
 
 pub enum RuntimeError {
   UnaryError(String),
@@ -6,7 +11,24 @@ pub enum RuntimeError {
   UndefinedIdentifier(String),
   WrongArgumentsForFunction(String, usize, usize),
   StatementError(String),
-  ReturnValue(RuntimeType)
+  ReturnValue(RuntimeType),
+  // Raised by `return f(...)` and handled by `RuntimeFunction::call`.
+  TailCall(Box<TailCall>),
+  InvalidArgument(String, String),
+  AssertionFailed(String, usize),
+  PropertyError(String),
+  ImportError(String),
+  IoError(String, String),
+  Exit(i32),
+  IntegerOverflow(usize),
+  // Carries the backtrace at the call that went over the limit.
+  StackOverflow(Vec<String>),
+  // Sandbox limits; see `SandboxConfig`.
+  StepLimitExceeded(u64),
+  Timeout(u128),
+  AllocationLimitExceeded(u64),
+  StringTooLong(usize),
+  CapabilityDenied(String, String)
 }
 impl RuntimeError {
   pub fn to_string(&self) -> String {
@@ -16,7 +38,40 @@ impl RuntimeError {
       RuntimeError::UndefinedIdentifier(name) => format!("Identifier '{}' is undefined", name),
       RuntimeError::WrongArgumentsForFunction(func_name, expected_len, got_len, ) => format!("Function '{}' was called with wrong number of arguments. Expected: {}, got: {}", func_name, expected_len, got_len),
       RuntimeError::StatementError(e) => format!("Statement failure: {}", e.to_string()),
-      RuntimeError::ReturnValue(v) => format!("Return error with value: {}", v.to_string())
+      RuntimeError::ReturnValue(v) => format!("Return error with value: {}", v.to_string()),
+      RuntimeError::TailCall(t) => format!("Return error with call to: {}", t.function.get_name()),
+      RuntimeError::InvalidArgument(func_name, m) => format!("Invalid argument for '{}': {}", func_name, m),
+      RuntimeError::AssertionFailed(m, line) => format!("Assertion failed: {}\n[line {}]", m, line),
+      RuntimeError::PropertyError(m) => m.to_owned(),
+      RuntimeError::ImportError(m) => format!("Import failed: {}", m),
+      RuntimeError::IoError(func_name, m) => format!("I/O error in '{}': {}", func_name, m),
+      RuntimeError::Exit(code) => format!("Exited with code {}", code),
+      RuntimeError::IntegerOverflow(line) => format!("Integer overflow.\n[line {}]", line),
+      RuntimeError::StackOverflow(trace) => format!("Stack overflow.\n{}", trace.join("\n")),
+      RuntimeError::StepLimitExceeded(max) => format!("Step limit of {} exceeded.", max),
+      RuntimeError::Timeout(ms) => format!("Time limit of {} ms exceeded.", ms),
+      RuntimeError::AllocationLimitExceeded(max) => format!("Allocation limit of {} exceeded.", max),
+      RuntimeError::StringTooLong(max) => format!("String length limit of {} exceeded.", max),
+      RuntimeError::CapabilityDenied(what, capability) => format!("{} needs the '{}' capability, which the sandbox does not allow.", what, capability)
     }
   }
+
+  // Errors raised while evaluating a statement's expression are reported as statement failures, except for the ones
+  // callers need to tell apart.
+  pub fn into_statement_error(self) -> RuntimeError {
+    match self {
+      RuntimeError::AssertionFailed(_, _) | RuntimeError::StackOverflow(_) => self,
+      _ if self.is_fatal() => self,
+      _ => RuntimeError::StatementError(self.to_string())
+    }
+  }
+
+  // Errors that end the script: `assert_throws` lets them through, so a sandboxed script can't catch its way past a
+  // limit.
+  pub fn is_fatal(&self) -> bool {
+    matches!(self,
+      RuntimeError::Exit(_) | RuntimeError::StepLimitExceeded(_) | RuntimeError::Timeout(_) |
+      RuntimeError::AllocationLimitExceeded(_) | RuntimeError::StringTooLong(_) | RuntimeError::CapabilityDenied(_, _)
+    )
+  }
 }
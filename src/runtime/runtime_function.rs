@@ -1,37 +1,78 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
 
-use crate::{environment::Environment, statement::{evaluator::StmtEvaluator, Statement}, token::Token};
+use crate::{environment::{Binding, Environment}, module::ModuleLoader, sandbox::Sandbox, statement::{evaluator::StmtEvaluator, Statement}, token::Token};
 
-use super::{runtime_error::RuntimeError, runtime_type::RuntimeType};
+use super::{call_stack::{CallFrame, CallStack}, garbage_collector::GarbageCollector, runtime_error::RuntimeError, runtime_hook::RuntimeHooks, runtime_type::RuntimeType};
 
 pub struct RuntimeFunction {
   fun_name: String,
   args: Vec<Rc<Token>>,
   body: Box<Statement>,
-  enclosing: Rc<RefCell<Environment>>
+  // Where names the function doesn't declare or capture are looked up: the global scope, or a module's.
+  top_level: Rc<RefCell<Environment>>,
+  // The variables of enclosing functions and blocks the body uses, shared with the scopes that declared them.
+  captures: Vec<(Rc<Token>, Binding)>,
+  // The file the function was written in. None for natives, which run as part of their caller.
+  file: Option<Rc<PathBuf>>
 }
 
 impl RuntimeFunction {
-  pub fn new(fun_name: String, args: Vec<Rc<Token>>, body: Box<Statement>, enclosing: Rc<RefCell<Environment>>) -> Self {
-    Self { fun_name, args, body, enclosing }
+  pub fn new(fun_name: String, args: Vec<Rc<Token>>, body: Box<Statement>, top_level: Rc<RefCell<Environment>>, captures: Vec<(Rc<Token>, Binding)>) -> Self {
+    GarbageCollector::track_env(&top_level);
+    for (_name, binding) in &captures {
+      GarbageCollector::track_binding(binding);
+    }
+    let file = if matches!(*body, Statement::Native(_)) { None } else { ModuleLoader::current_file() };
+    Self { fun_name, args, body, top_level, captures, file }
+  }
+
+  pub fn get_top_level(&self) -> Rc<RefCell<Environment>> {
+    self.top_level.clone()
+  }
+
+  pub fn get_captures(&self) -> Vec<Binding> {
+    self.captures.iter().map(|(_name, binding)| binding.clone()).collect()
   }
 
   pub fn get_name(&self) -> String {
     self.fun_name.to_owned()
   }
 
-  pub fn call(&self, args_values: Vec<Rc<RuntimeType>>) -> Result<RuntimeType, RuntimeError> { 
-    if self.args.len() != args_values.len() {
-      return Err(RuntimeError::WrongArgumentsForFunction(self.fun_name.clone(), self.args.len(), args_values.len()));
-    }
+  pub fn is_native(&self) -> bool {
+    matches!(*self.body, Statement::Native(_))
+  }
 
-    let local_env = Rc::new(RefCell::new(Environment::local(self.enclosing.clone())));
+  pub fn call(&self, args_values: Vec<Rc<RuntimeType>>, call_line: usize, caller_env: &Rc<RefCell<Environment>>) -> Result<RuntimeType, RuntimeError> { 
+    self.check_arity(&args_values)?;
+    CallStack::check_depth(call_line)?;
 
-    for (arg_name, arg_value) in self.args.iter().zip(args_values.iter()) {
-      local_env.borrow_mut().define(arg_name.lexeme.to_string(), arg_value.clone());
+    let caller_file = ModuleLoader::current_file();
+    CallStack::push(CallFrame { fun_name: self.fun_name.clone(), call_line, caller_env: caller_env.clone(), caller_file: caller_file.clone(), tail_calls: 0 });
+    self.enter_file();
+    RuntimeHooks::on_call(&self.fun_name, self.is_native(), call_line);
+    let mut result = self.run(args_values);
+    RuntimeHooks::on_return(&self.fun_name);
+
+    // A `return f(...)` in the body comes back here and runs in the same frame, so tail recursion doesn't grow the
+    // Rust stack. The frame keeps the original caller.
+    while let Err(RuntimeError::TailCall(tail_call)) = result {
+      let TailCall { function, args_values, call_line } = *tail_call;
+      if let Err(e) = function.check_arity(&args_values) {
+        result = Err(e);
+        break;
+      }
+
+      CallStack::tail_call(function.get_name());
+      function.enter_file();
+      RuntimeHooks::on_call(&function.fun_name, function.is_native(), call_line);
+      result = function.run(args_values);
+      RuntimeHooks::on_return(&function.fun_name);
     }
 
-    match StmtEvaluator::evaluate(&self.body, &local_env) {
+    ModuleLoader::set_current_file(caller_file);
+    CallStack::pop();
+
+    match result {
       Ok(v) => Ok(v),
       Err(e) => match e {
         RuntimeError::ReturnValue(v) => Ok(v),
@@ -39,4 +80,44 @@ impl RuntimeFunction {
       }
     }
   }
+
+  // Hooks and relative imports see the file the running code comes from.
+  fn enter_file(&self) {
+    if self.file.is_some() {
+      ModuleLoader::set_current_file(self.file.clone());
+    }
+  }
+
+  fn check_arity(&self, args_values: &[Rc<RuntimeType>]) -> Result<(), RuntimeError> {
+    if self.args.len() != args_values.len() {
+      return Err(RuntimeError::WrongArgumentsForFunction(self.fun_name.clone(), self.args.len(), args_values.len()));
+    }
+
+    Ok(())
+  }
+
+  // Binds the arguments, then the captured variables, in a new scope and runs the body. `ReturnValue` and `TailCall`
+  // are left to `call`.
+  fn run(&self, args_values: Vec<Rc<RuntimeType>>) -> Result<RuntimeType, RuntimeError> {
+    Sandbox::allocate(1)?;
+
+    let local_env = Rc::new(RefCell::new(Environment::local(self.top_level.clone())));
+
+    for (arg_name, arg_value) in self.args.iter().zip(args_values.iter()) {
+      local_env.borrow_mut().define(arg_name, arg_value.clone());
+    }
+
+    for (name, binding) in &self.captures {
+      local_env.borrow_mut().bind(name, binding.clone());
+    }
+
+    StmtEvaluator::evaluate(&self.body, &local_env)
+  }
+}
+
+// A `return f(...)` waiting to run in place of the function that returns it.
+pub struct TailCall {
+  pub function: Rc<RuntimeFunction>,
+  pub args_values: Vec<Rc<RuntimeType>>,
+  pub call_line: usize
 }
\ No newline at end of file
@@ -0,0 +1,85 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{environment::Environment, statement::Statement, token::Token};
+
+use super::runtime_error::RuntimeError;
+
+// Observes execution. Installed hooks are consulted by `StmtEvaluator` before every statement that maps to a source line.
+pub trait RuntimeHook {
+  fn before_statement(&mut self, statement: &Statement, line: usize, env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError>;
+
+  fn on_call(&mut self, _fun_name: &str, _native: bool, _call_line: usize) {}
+
+  fn on_return(&mut self, _fun_name: &str) {}
+
+  // `site` identifies the if/while/logical expression, `branch` is 0 for then/loop entered/short-circuit and 1 for the other path.
+  fn on_branch(&mut self, _site: usize, _line: usize, _branch: usize) {}
+
+  // Returns true when the hook took care of program output that would otherwise go to stdout.
+  fn on_output(&mut self, _text: &str) -> bool {
+    false
+  }
+}
+
+thread_local! {
+  static HOOKS: RefCell<Vec<Box<dyn RuntimeHook>>> = const { RefCell::new(Vec::new()) };
+}
+
+pub struct RuntimeHooks;
+
+impl RuntimeHooks {
+  pub fn install(hook: Box<dyn RuntimeHook>) {
+    HOOKS.with(|hooks| hooks.borrow_mut().push(hook));
+  }
+
+  pub fn before_statement(statement: &Statement, env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    let line = match statement.line() {
+      Some(line) => line,
+      None => return Ok(())
+    };
+
+    RuntimeHooks::each(|hook| hook.before_statement(statement, line, env)).unwrap_or(Ok(()))
+  }
+
+  pub fn on_call(fun_name: &str, native: bool, call_line: usize) {
+    RuntimeHooks::each(|hook| { hook.on_call(fun_name, native, call_line); Ok(()) });
+  }
+
+  pub fn on_return(fun_name: &str) {
+    RuntimeHooks::each(|hook| { hook.on_return(fun_name); Ok(()) });
+  }
+
+  pub fn on_branch(token: Option<&Rc<Token>>, branch: usize) {
+    if let Some(token) = token {
+      RuntimeHooks::each(|hook| { hook.on_branch(RuntimeHooks::site(token), token.line, branch); Ok(()) });
+    }
+  }
+
+  // Runs `f` for every hook, stopping at the first error. Returns None when no hooks are installed or when called
+  // from inside a hook (e.g. a debugger evaluating an expression), in which case the hooks are not re-entered.
+  fn each(mut f: impl FnMut(&mut Box<dyn RuntimeHook>) -> Result<(), RuntimeError>) -> Option<Result<(), RuntimeError>> {
+    HOOKS.with(|hooks| {
+      let mut hooks = hooks.try_borrow_mut().ok()?;
+      if hooks.is_empty() {
+        return None;
+      }
+
+      Some(hooks.iter_mut().try_for_each(&mut f))
+    })
+  }
+
+  // Tokens are shared between clones of the AST, so their address identifies a source location.
+  pub fn site(token: &Rc<Token>) -> usize {
+    Rc::as_ptr(token) as usize
+  }
+
+  // Writes program output, letting hooks redirect it first.
+  pub fn print(text: &str) {
+    let mut handled = false;
+    RuntimeHooks::each(|hook| { handled = handled || hook.on_output(text); Ok(()) });
+
+    if !handled {
+      println!("{}", text);
+    }
+  }
+}
@@ -0,0 +1,29 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::environment::Environment;
+
+use super::runtime_type::RuntimeType;
+
+// An imported file. Its exports are the bindings of its top-level scope.
+pub struct RuntimeModule {
+  path: String,
+  env: Rc<RefCell<Environment>>
+}
+
+impl RuntimeModule {
+  pub fn new(path: String, env: Rc<RefCell<Environment>>) -> Self {
+    Self { path, env }
+  }
+
+  pub fn get_path(&self) -> String {
+    self.path.to_owned()
+  }
+
+  pub fn get_env(&self) -> Rc<RefCell<Environment>> {
+    self.env.clone()
+  }
+
+  pub fn get_export(&self, name: &str) -> Option<Rc<RuntimeType>> {
+    self.env.borrow().get_local(name)
+  }
+}
@@ -0,0 +1,64 @@
+// The one place numbers are turned into text, used by `print`, `evaluate`, string conversion and the literal column
+// of `tokenize`.
+pub struct RuntimeNumber;
+
+impl RuntimeNumber {
+  // Integral values print without a fraction ("3"), magnitudes from 1e21 up or below 1e-6 in exponent form ("1e21",
+  // "1.5e-7"), everything else as the shortest decimal that reads back as the same value. The output is itself a valid literal,
+  // except for the non-finite values and the sign of negative numbers.
+  pub fn to_string(value: f64) -> String {
+    if value.is_nan() {
+      return String::from("NaN");
+    }
+
+    if value.is_infinite() {
+      return String::from(if value > 0. { "Infinity" } else { "-Infinity" });
+    }
+
+    if value == 0. {
+      return String::from(if value.is_sign_negative() { "-0" } else { "0" });
+    }
+
+    if (1e-6..1e21).contains(&value.abs()) {
+      format!("{}", value)
+    } else {
+      format!("{:e}", value)
+    }
+  }
+
+  // Exact comparison: 2^53 + 1 is not equal to the float 2^53.
+  pub fn integer_equals(integer: i64, number: f64) -> bool {
+    number.fract() == 0. && number >= i64::MIN as f64 && number < i64::MAX as f64 && number as i64 == integer
+  }
+
+  // Value of an integer literal (`42`, `1_000`, `0xFF`, `0b101`). None for literals with a fraction or exponent and for
+  // values that do not fit in an i64, which are numbers instead.
+  pub fn parse_integer(lexeme: &str) -> Option<i64> {
+    let digits = lexeme.replace('_', "");
+    let lower = digits.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x") {
+      return i64::from_str_radix(hex, 16).ok();
+    }
+    if let Some(binary) = lower.strip_prefix("0b") {
+      return i64::from_str_radix(binary, 2).ok();
+    }
+    // The lexer never produces a sign, but constants folded by the optimizer can be negative.
+    if !digits.strip_prefix('-').unwrap_or(&digits).chars().all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+
+    digits.parse::<i64>().ok()
+  }
+
+  // Like `to_string`, but integral values keep a ".0" to show they are numbers ("3.0").
+  pub fn to_literal(value: f64) -> String {
+    let string = RuntimeNumber::to_string(value);
+
+    if string.chars().all(|c| c.is_ascii_digit() || c == '-') {
+      format!("{}.0", string)
+    } else {
+      string
+    }
+  }
+}
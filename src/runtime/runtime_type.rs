@@ -1,24 +1,109 @@
-use std::rc::Rc;
+#![allow(clippy::inherent_to_string)]
 
-use super::runtime_function::RuntimeFunction;
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use super::{runtime_bigint::RuntimeBigInt, runtime_function::RuntimeFunction, runtime_module::RuntimeModule, runtime_number::RuntimeNumber};
 
 #[derive(Clone)]
 pub enum RuntimeType {
   Boolean(bool),
   String(String),
   Number(f64),
+  // Produced by integer literals. Arithmetic between integers stays integral, mixing with a number gives a number.
+  Integer(i64),
+  // `123n` literals. Mixed with an integer the result is a bigint, mixed with a number it is a number.
+  BigInt(Rc<RuntimeBigInt>),
   Function(Rc<RuntimeFunction>),
+  Module(Rc<RuntimeModule>),
+  // Shared and mutable, like the lists returned by `split`.
+  List(Rc<RefCell<Vec<RuntimeType>>>),
   Nil()
 }
 
+// A list is identified by its address while checking for cycles.
+type ListId = *const RefCell<Vec<RuntimeType>>;
+
 impl RuntimeType {
   pub fn to_string(&self) -> String {
+    self.display(&mut HashSet::new())
+  }
+
+  // `visiting` holds the lists being printed, so a list that contains itself prints `[...]` there.
+  fn display(&self, visiting: &mut HashSet<ListId>) -> String {
     match self {
       RuntimeType::Boolean(v) => v.to_string(),
       RuntimeType::String(v) => v.to_string(),
-      RuntimeType::Number(v) => v.to_string(),
+      RuntimeType::Number(v) => RuntimeNumber::to_string(*v),
+      RuntimeType::Integer(v) => v.to_string(),
+      RuntimeType::BigInt(v) => v.to_string(),
       RuntimeType::Nil() => String::from("nil"),
-      RuntimeType::Function(f) => format!("<fn {}>", f.get_name())
+      RuntimeType::Function(f) => format!("<fn {}>", f.get_name()),
+      RuntimeType::Module(m) => format!("<module {}>", m.get_path()),
+      RuntimeType::List(l) => {
+        if !visiting.insert(Rc::as_ptr(l)) {
+          return String::from("[...]");
+        }
+
+        let elements = l.borrow().iter().map(|v| v.display(visiting)).collect::<Vec<String>>().join(", ");
+        visiting.remove(&Rc::as_ptr(l));
+        format!("[{}]", elements)
+      }
+    }
+  }
+
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      RuntimeType::Boolean(_) => "boolean",
+      RuntimeType::String(_) => "string",
+      RuntimeType::Number(_) => "number",
+      RuntimeType::Integer(_) => "integer",
+      RuntimeType::BigInt(_) => "bigint",
+      RuntimeType::Nil() => "nil",
+      RuntimeType::Function(_) => "function",
+      RuntimeType::Module(_) => "module",
+      RuntimeType::List(_) => "list"
+    }
+  }
+
+  // Same type and value. Functions and modules are equal only to themselves, lists compare element-wise.
+  pub fn equals(&self, other: &RuntimeType) -> bool {
+    self.equals_visiting(other, &mut HashSet::new())
+  }
+
+  // `visiting` holds the pairs of lists being compared. Meeting a pair again means following a cycle, which cannot
+  // find a difference that the rest of the comparison wouldn't.
+  fn equals_visiting(&self, other: &RuntimeType, visiting: &mut HashSet<(ListId, ListId)>) -> bool {
+    match (self, other) {
+      (RuntimeType::Boolean(a), RuntimeType::Boolean(b)) => a == b,
+      (RuntimeType::String(a), RuntimeType::String(b)) => a == b,
+      (RuntimeType::Number(a), RuntimeType::Number(b)) => a == b,
+      (RuntimeType::Integer(a), RuntimeType::Integer(b)) => a == b,
+      (RuntimeType::Integer(a), RuntimeType::Number(b)) | (RuntimeType::Number(b), RuntimeType::Integer(a)) => RuntimeNumber::integer_equals(*a, *b),
+      (RuntimeType::BigInt(a), RuntimeType::BigInt(b)) => a == b,
+      (RuntimeType::BigInt(a), RuntimeType::Integer(b)) | (RuntimeType::Integer(b), RuntimeType::BigInt(a)) => **a == RuntimeBigInt::from_i64(*b),
+      (RuntimeType::BigInt(a), RuntimeType::Number(b)) | (RuntimeType::Number(b), RuntimeType::BigInt(a)) => Some((**a).clone()) == RuntimeBigInt::from_f64(*b),
+      (RuntimeType::Function(a), RuntimeType::Function(b)) => Rc::ptr_eq(a, b),
+      (RuntimeType::Module(a), RuntimeType::Module(b)) => Rc::ptr_eq(a, b),
+      (RuntimeType::List(a), RuntimeType::List(b)) => {
+        if Rc::ptr_eq(a, b) || !visiting.insert((Rc::as_ptr(a), Rc::as_ptr(b))) {
+          return true;
+        }
+
+        let (a, b) = (a.borrow(), b.borrow());
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals_visiting(y, visiting))
+      },
+      (RuntimeType::Nil(), RuntimeType::Nil()) => true,
+      _ => false
+    }
+  }
+
+  // Integers, bigints and numbers as a float, for operations that do not keep integers exact.
+  pub fn as_number(&self) -> Option<f64> {
+    match self {
+      RuntimeType::Number(n) => Some(*n),
+      RuntimeType::Integer(i) => Some(*i as f64),
+      RuntimeType::BigInt(i) => Some(i.to_f64()),
+      _ => None
     }
   }
 
@@ -0,0 +1,191 @@
+use std::{cell::RefCell, time::{Duration, Instant}};
+
+use crate::runtime::runtime_error::RuntimeError;
+
+// What a script may reach outside the interpreter. Natives and imports that need a capability fail without it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Capability {
+  Io,
+  Env,
+  Clock,
+  Import
+}
+
+impl Capability {
+  pub fn parse(name: &str) -> Option<Capability> {
+    match name {
+      "io" => Some(Capability::Io),
+      "env" => Some(Capability::Env),
+      "clock" => Some(Capability::Clock),
+      "import" => Some(Capability::Import),
+      _ => None
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Capability::Io => "io",
+      Capability::Env => "env",
+      Capability::Clock => "clock",
+      Capability::Import => "import"
+    }
+  }
+
+  // The capability a native function needs, if any.
+  fn of_native(name: &str) -> Option<Capability> {
+    match name {
+      "read_line" | "read_all_stdin" | "read_file" | "write_file" | "append_file" | "file_exists" | "list_dir" => Some(Capability::Io),
+      "getenv" | "setenv" => Some(Capability::Env),
+      "clock" => Some(Capability::Clock),
+      _ => None
+    }
+  }
+}
+
+// Limits for running untrusted scripts. `None` means unlimited; `capabilities: None` allows everything.
+#[derive(Clone, Default)]
+pub struct SandboxConfig {
+  // Statements executed, counting each run of a loop body.
+  pub max_steps: Option<u64>,
+  pub timeout: Option<Duration>,
+  // Scopes, strings and list elements created.
+  pub max_allocations: Option<u64>,
+  // In chars.
+  pub max_string_length: Option<usize>,
+  pub capabilities: Option<Vec<Capability>>
+}
+
+impl SandboxConfig {
+  // Defaults of `--sandbox`: bounded everything and no capabilities. Individual options override them.
+  pub fn restricted() -> Self {
+    Self {
+      max_steps: Some(10_000_000),
+      timeout: Some(Duration::from_secs(5)),
+      max_allocations: Some(1_000_000),
+      max_string_length: Some(1_000_000),
+      capabilities: Some(Vec::new())
+    }
+  }
+
+  pub fn is_unrestricted(&self) -> bool {
+    self.max_steps.is_none() && self.timeout.is_none() && self.max_allocations.is_none() && self.max_string_length.is_none() && self.capabilities.is_none()
+  }
+}
+
+struct SandboxState {
+  config: SandboxConfig,
+  started: Instant,
+  steps: u64,
+  allocations: u64
+}
+
+thread_local! {
+  static STATE: RefCell<Option<SandboxState>> = const { RefCell::new(None) };
+}
+
+impl SandboxState {
+  fn check_time(&self) -> Result<(), RuntimeError> {
+    match self.config.timeout {
+      Some(timeout) if self.started.elapsed() > timeout => Err(RuntimeError::Timeout(timeout.as_millis())),
+      _ => Ok(())
+    }
+  }
+}
+
+// Enforces a `SandboxConfig`. Every check passes when no sandbox is enabled.
+pub struct Sandbox;
+
+impl Sandbox {
+  pub fn enable(config: SandboxConfig) {
+    if config.is_unrestricted() {
+      return;
+    }
+
+    STATE.with(|state| *state.borrow_mut() = Some(SandboxState { config, started: Instant::now(), steps: 0, allocations: 0 }));
+  }
+
+  // Runs `f` with the sandbox lifted, for work the interpreter does on its own behalf.
+  pub fn suspended<T>(f: impl FnOnce() -> T) -> T {
+    let state = STATE.with(|state| state.borrow_mut().take());
+    let result = f();
+    STATE.with(|cell| *cell.borrow_mut() = state);
+    result
+  }
+
+  fn with<T>(f: impl FnOnce(&mut SandboxState) -> Result<T, RuntimeError>, default: T) -> Result<T, RuntimeError> {
+    STATE.with(|state| match state.borrow_mut().as_mut() {
+      Some(state) => f(state),
+      None => Ok(default)
+    })
+  }
+
+  // Called for every statement, blocks and loop bodies included, so an empty loop still runs out of steps.
+  pub fn step() -> Result<(), RuntimeError> {
+    Sandbox::with(|state| {
+      state.steps += 1;
+
+      if let Some(max_steps) = state.config.max_steps {
+        if state.steps > max_steps {
+          return Err(RuntimeError::StepLimitExceeded(max_steps));
+        }
+      }
+
+      state.check_time()
+    }, ())
+  }
+
+  // For operations that can run long inside a single statement, like multiplying huge bigints.
+  pub fn check_time() -> Result<(), RuntimeError> {
+    Sandbox::with(|state| state.check_time(), ())
+  }
+
+  // Called for each new scope, string or list element.
+  pub fn allocate(count: usize) -> Result<(), RuntimeError> {
+    Sandbox::with(|state| {
+      state.allocations += count as u64;
+
+      match state.config.max_allocations {
+        Some(max_allocations) if state.allocations > max_allocations => Err(RuntimeError::AllocationLimitExceeded(max_allocations)),
+        _ => Ok(())
+      }
+    }, ())
+  }
+
+  // Checks a string about to be created and counts it as an allocation.
+  pub fn check_string(length: usize) -> Result<(), RuntimeError> {
+    Sandbox::check_length(length)?;
+    Sandbox::allocate(1)
+  }
+
+  // Checks the length of a string being built, before it is complete.
+  pub fn check_length(length: usize) -> Result<(), RuntimeError> {
+    Sandbox::with(|state| match state.config.max_string_length {
+      Some(max_length) if length > max_length => Err(RuntimeError::StringTooLong(max_length)),
+      _ => Ok(())
+    }, ())
+  }
+
+  // A bigint of that many bits is limited like the decimal string it prints as. Called before computing a product or a
+  // power, whose size is known up front, so an oversized one fails without doing the work.
+  pub fn check_bigint(bits: u64) -> Result<(), RuntimeError> {
+    Sandbox::check_length((bits as f64 * std::f64::consts::LOG10_2).ceil() as usize)
+  }
+
+  pub fn check_native(name: &str) -> Result<(), RuntimeError> {
+    match Capability::of_native(name) {
+      Some(capability) => Sandbox::require(capability, &format!("'{}'", name)),
+      None => Ok(())
+    }
+  }
+
+  pub fn check_import(path: &str) -> Result<(), RuntimeError> {
+    Sandbox::require(Capability::Import, &format!("import \"{}\"", path))
+  }
+
+  fn require(capability: Capability, what: &str) -> Result<(), RuntimeError> {
+    Sandbox::with(|state| match &state.config.capabilities {
+      Some(allowed) if !allowed.contains(&capability) => Err(RuntimeError::CapabilityDenied(what.to_owned(), capability.name().to_owned())),
+      _ => Ok(())
+    }, ())
+  }
+}
@@ -1,6 +1,12 @@
+#![allow(
+  clippy::needless_borrow,
+  clippy::needless_return,
+  clippy::question_mark
+)]
+
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{environment::Environment, expression::evaluator::ExprEvaluator, runtime::{runtime_error::RuntimeError, runtime_function::RuntimeFunction, runtime_type::RuntimeType}};
+use crate::{environment::Environment, expression::{evaluator::ExprEvaluator, Expression}, module::ModuleLoader, sandbox::Sandbox, runtime::{call_stack::CallStack, garbage_collector::GarbageCollector, runtime_error::RuntimeError, runtime_function::{RuntimeFunction, TailCall}, runtime_hook::RuntimeHooks, runtime_type::RuntimeType}};
 
 use super::Statement;
 
@@ -8,40 +14,59 @@ pub struct StmtEvaluator;
 
 impl StmtEvaluator {
   pub fn evaluate(statement: &Statement, env: &Rc<RefCell<Environment>>) -> Result<RuntimeType, RuntimeError> {
+    Sandbox::step()?;
+    GarbageCollector::safepoint(env);
+    RuntimeHooks::before_statement(statement, env)?;
+
     match statement {
       Statement::Empty() => Ok(RuntimeType::Nil()),
       Statement::Print(e) => {
         match ExprEvaluator::evaluate(e, env) {
           Ok(t) => {
-            println!("{}", t.to_string());
+            RuntimeHooks::print(&t.to_string());
             Ok(RuntimeType::Nil())
           },
-          Err(e) => Err(RuntimeError::StatementError(e.to_string()))
+          Err(e) => Err(e.into_statement_error())
         }
       },
       Statement::Expression(e) => {
         match ExprEvaluator::evaluate(e, env) {
           Ok(_r) => Ok(RuntimeType::Nil()),
-          Err(e) => Err(RuntimeError::StatementError(e.to_string()))
+          Err(e) => Err(e.into_statement_error())
         }
       },
       Statement::Var(token, e) => {
         match ExprEvaluator::evaluate(e, env) {
           Ok(t) => {
-            env.borrow_mut().define(token.lexeme.to_string(), Rc::new(t));
+            env.borrow_mut().define(token, Rc::new(t));
             return Ok(RuntimeType::Nil());
           },
-          Err(e) => Err(RuntimeError::StatementError(e.to_string()))
+          Err(e) => Err(e.into_statement_error())
         }
       },
-      Statement::Function(func_name, args_names, body) => {
-        let fun = RuntimeFunction::new(func_name.lexeme.to_string(), args_names.clone(), body.clone(), env.clone());
+      Statement::Function(func_name, args_names, body, captures) => {
+        let captured = captures.variables.iter()
+          .map(|(name, depth, slot)| {
+            let binding = env.borrow().binding_at(*depth, *slot);
+            (name.clone(), binding.unwrap_or_else(|| Rc::new(RefCell::new(Rc::new(RuntimeType::Nil())))))
+          })
+          .collect();
+        let top_level = Environment::ancestor(env, captures.top_level);
+        let fun = RuntimeFunction::new(func_name.lexeme.to_string(), args_names.clone(), body.clone(), top_level, captured);
         let fun_type = RuntimeType::Function(Rc::new(fun));
-        env.borrow_mut().define(func_name.lexeme.to_string(), Rc::new(fun_type));
+        env.borrow_mut().define(func_name, Rc::new(fun_type));
         return Ok(RuntimeType::Nil());
       },
       Statement::Block(statements) => {
+        Sandbox::allocate(1)?;
         let local_env = Rc::new(RefCell::new(Environment::local(env.clone())));
+
+        // Functions exist from the start of the block, so closures declared before one can capture it.
+        for statement in statements.as_ref() {
+          if let Statement::Function(func_name, _args, _body, _captures) = statement {
+            local_env.borrow_mut().define(func_name, Rc::new(RuntimeType::Nil()));
+          }
+        }
         
         for statement in statements.as_ref() {
           let res = StmtEvaluator::evaluate(&statement, &Rc::clone(&local_env));
@@ -56,15 +81,17 @@ impl StmtEvaluator {
       Statement::If(expr, then_stmt, else_stmt) => {
         match ExprEvaluator::evaluate(expr, &env) {
           Ok(condition) => {
+            RuntimeHooks::on_branch(expr.token(), if condition.is_truthy() { 0 } else { 1 });
             StmtEvaluator::evaluate(if condition.is_truthy() { &then_stmt } else { &else_stmt }, &env)
           },
-          Err(e) => Err(RuntimeError::StatementError(e.to_string()))
+          Err(e) => Err(e.into_statement_error())
         }
       },
       Statement::While(expr, stmt) => {
         loop {
           match ExprEvaluator::evaluate(&expr, &env) {
             Ok(condition) => {
+              RuntimeHooks::on_branch(expr.token(), if condition.is_truthy() { 0 } else { 1 });
               if condition.is_truthy() {
                 let res = StmtEvaluator::evaluate(&stmt, &env);
   
@@ -75,19 +102,72 @@ impl StmtEvaluator {
                 return Ok(RuntimeType::Nil());
               }
             },
-            Err(e) => { return Err(RuntimeError::StatementError(e.to_string())); }
+            Err(e) => { return Err(e.into_statement_error()); }
           } 
         }
       },
+      Statement::For(initializer, condition, increment, body) => {
+        let loop_env = Rc::new(RefCell::new(Environment::local(env.clone())));
+        StmtEvaluator::evaluate(initializer, &loop_env)?;
+
+        // Every iteration gets its own copy of the loop variables; the increment runs on the next iteration's copy.
+        let mut iteration_env = Rc::new(RefCell::new(loop_env.borrow().copy()));
+        loop {
+          let condition_value = ExprEvaluator::evaluate(condition, &iteration_env).map_err(|e| e.into_statement_error())?;
+          RuntimeHooks::on_branch(condition.token(), if condition_value.is_truthy() { 0 } else { 1 });
+          if !condition_value.is_truthy() {
+            return Ok(RuntimeType::Nil());
+          }
+
+          StmtEvaluator::evaluate(body, &iteration_env)?;
+
+          Sandbox::allocate(1)?;
+          let next_env = iteration_env.borrow().copy();
+          iteration_env = Rc::new(RefCell::new(next_env));
+          ExprEvaluator::evaluate(increment, &iteration_env).map_err(|e| e.into_statement_error())?;
+        }
+      },
       Statement::Return(expr) => {
+        // Inside a function, a call to another Lox function is handed back to `RuntimeFunction::call` to run in place
+        // of the current one.
+        if let Expression::Call(callee, arguments) = expr.as_ref() {
+          if CallStack::depth() > 0 {
+            let (function, args_values) = ExprEvaluator::evaluate_call(callee, arguments, env)?;
+            let call_line = callee.line().unwrap_or(0);
+
+            if function.is_native() {
+              return Err(RuntimeError::ReturnValue(function.call(args_values, call_line, env)?));
+            }
+            return Err(RuntimeError::TailCall(Box::new(TailCall { function, args_values, call_line })));
+          }
+        }
+
         match ExprEvaluator::evaluate(expr, env) {
           Ok(value) => Err(RuntimeError::ReturnValue(value)),
           Err(e) => Err(e)
         }
       },
-      Statement::Native(fun) => {
-        return fun();
-      }
+      Statement::Import(path, alias) => {
+        // The module's code runs in its own scope, which doesn't lead back to this one.
+        let _importer = GarbageCollector::root_env(env);
+        let module = ModuleLoader::load(&path.literal)?;
+        env.borrow_mut().define(alias, Rc::new(RuntimeType::Module(module)));
+        Ok(RuntimeType::Nil())
+      },
+      Statement::ImportFrom(path, names) => {
+        let _importer = GarbageCollector::root_env(env);
+        let module = ModuleLoader::load(&path.literal)?;
+
+        for name in names {
+          match module.get_export(&name.lexeme) {
+            Some(value) => env.borrow_mut().define(name, value),
+            None => return Err(RuntimeError::ImportError(format!("'{}' is not exported by {}.\n[line {}]", name.lexeme, path.literal, name.line)))
+          }
+        }
+
+        Ok(RuntimeType::Nil())
+      },
+      Statement::Native(fun) => fun(env)
     }
   }
 }
\ No newline at end of file
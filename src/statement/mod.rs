@@ -1,10 +1,19 @@
+#![allow(
+  clippy::box_collection,
+  clippy::inherent_to_string,
+  clippy::to_string_in_format_args,
+  clippy::useless_format
+)]
+
 pub mod evaluator;
 pub mod parser;
 
 
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{environment::Environment, expression::Expression, runtime::{runtime_error::RuntimeError, runtime_type::RuntimeType}, token::Token};
 
-use crate::{expression::Expression, runtime::{runtime_error::RuntimeError, runtime_type::RuntimeType}, token::Token};
+pub type NativeFn = dyn Fn(&Rc<RefCell<Environment>>) -> Result<RuntimeType, RuntimeError>;
 
 #[derive(Clone)]
 pub enum Statement {
@@ -12,17 +21,49 @@ pub enum Statement {
   Print(Box<Expression>),
   Expression(Box<Expression>),
   Var(Rc<Token>, Box<Expression>),
-  Function(Rc<Token>, Vec<Rc<Token>>, Box<Statement>),
+  Function(Rc<Token>, Vec<Rc<Token>>, Box<Statement>, Captures),
   Block(Box<Vec<Statement>>),
   If(Box<Expression>, Box<Statement>, Box<Statement>),
   While(Box<Expression>, Box<Statement>),
+  // for (initializer; condition; increment) body
+  For(Box<Statement>, Box<Expression>, Box<Expression>, Box<Statement>),
   Return(Box<Expression>),
+  // import "path" as alias;
+  Import(Rc<Token>, Rc<Token>),
+  // from "path" import name, name;
+  ImportFrom(Rc<Token>, Vec<Rc<Token>>),
   
-  // Used for native functions.
-  Native(Rc<dyn Fn() -> Result<RuntimeType, RuntimeError>>)
+  // Used for native functions. Evaluated in the call's local environment, where the arguments are bound.
+  Native(Rc<NativeFn>)
+}
+
+// What a function uses from the scopes around it, filled in by the resolver. `variables` are the ones declared in
+// enclosing functions and blocks, each with how many scopes up from the declaration it lives and its slot there; the
+// function keeps only their bindings. Every other name is looked up in the top-level scope, `top_level` scopes up.
+#[derive(Clone, Default)]
+pub struct Captures {
+  pub variables: Vec<(Rc<Token>, usize, usize)>,
+  pub top_level: usize
 }
 
 impl Statement {
+  // Source line the statement starts on. Blocks and synthetic statements have none.
+  pub fn line(&self) -> Option<usize> {
+    match self {
+      Statement::Print(expression) => expression.line(),
+      Statement::Expression(expression) => expression.line(),
+      Statement::Var(token, _expression) => Some(token.line),
+      Statement::Function(func_name, _args, _body, _captures) => Some(func_name.line),
+      Statement::If(expr, _then_stmt, _else_stmt) => expr.line(),
+      Statement::While(expr, _stmt) => expr.line(),
+      Statement::For(initializer, condition, _increment, _body) => condition.line().or(initializer.line()),
+      Statement::Return(expr) => expr.line(),
+      Statement::Import(path, _alias) => Some(path.line),
+      Statement::ImportFrom(path, _names) => Some(path.line),
+      Statement::Empty() | Statement::Block(_) | Statement::Native(_) => None
+    }
+  }
+
   pub fn to_string(&self) -> String {
     match self {
       Statement::Empty() => String::new(),
@@ -35,7 +76,7 @@ impl Statement {
       Statement::Var(token, expression) => {
         format!("Var: {}, {}", token.lexeme, expression.to_string())
       },
-      Statement::Function(func_name, args, body) => {
+      Statement::Function(func_name, args, body, _captures) => {
         format!("Function: name = {}, args = {}, body = {}", func_name.lexeme, args.iter().map(|a| a.to_str()).collect::<Vec<_>>().join(", ").to_string(), body.to_string())
       },
       Statement::Block(statements) => {
@@ -52,9 +93,18 @@ impl Statement {
       Statement::While(expr, stmt) => {
         format!("While:\n Condition: {};\n Statement: {}", expr.to_string(), stmt.to_string())
       },
+      Statement::For(initializer, condition, increment, body) => {
+        format!("For:\n Initializer: {};\n Condition: {};\n Increment: {};\n Statement: {}", initializer.to_string(), condition.to_string(), increment.to_string(), body.to_string())
+      },
       Statement::Return(expr) => {
         format!("Return: \n {}", expr.to_string())
-      }
+      },
+      Statement::Import(path, alias) => {
+        format!("Import: path = {}, as = {}", path.literal, alias.lexeme)
+      },
+      Statement::ImportFrom(path, names) => {
+        format!("Import: path = {}, names = {}", path.literal, names.iter().map(|n| n.lexeme.to_string()).collect::<Vec<_>>().join(", "))
+      },
       Statement::Native(_fun) => {
         format!("Native function")
       },
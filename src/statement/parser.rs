@@ -1,3 +1,10 @@
+#![allow(
+  clippy::collapsible_match,
+  clippy::needless_return,
+  clippy::op_ref,
+  clippy::single_match
+)]
+
 use std::rc::Rc;
 
 use crate::expression::Expression;
@@ -5,7 +12,7 @@ use crate::expression::parser::ExprParser;
 use crate::parser::parser_error::ParserError;
 use crate::parser::parser_utils::ParserUtils;
 use crate::token::{Token, TokenType};
-use crate::statement::Statement;
+use crate::statement::{Captures, Statement};
 
 pub struct StmtParser;
 
@@ -28,6 +35,11 @@ impl StmtParser {
     match tokens[*index].token_type {
       TokenType::Fun => StmtParser::fun_declaration(tokens, index),
       TokenType::Var => StmtParser::var_declaration(tokens, index),
+      TokenType::Import => StmtParser::import_declaration(tokens, index),
+      // `from` is only a keyword when followed by a path, so it stays usable as a name.
+      TokenType::Identifier if tokens[*index].lexeme == "from" && tokens.get(*index + 1).map(|t| t.token_type == TokenType::String).unwrap_or(false) => {
+        StmtParser::import_from_declaration(tokens, index)
+      },
       _ => StmtParser::statement(tokens, index)
     }
   }
@@ -44,7 +56,7 @@ impl StmtParser {
             }
 
             match StmtParser::statement(tokens, index) {
-              Ok(body) => return Ok(Statement::Function(func_name, args, Box::new(body))),
+              Ok(body) => return Ok(Statement::Function(func_name, args, Box::new(body), Captures::default())),
               Err(e) => return Err(e)
             }
           },
@@ -94,8 +106,10 @@ impl StmtParser {
     match ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
       Some(identifier) => match ParserUtils::match_advance(tokens, index, &[TokenType::Equal]) {
         Some(_equal) => {
-          let value = StmtParser::expression(tokens, index, &Some(TokenType::Semicolon), false);
-          Ok(Statement::Var(identifier, Box::new(value.unwrap())))
+          match StmtParser::expression(tokens, index, &Some(TokenType::Semicolon), false) {
+            Ok(value) => Ok(Statement::Var(identifier, Box::new(value))),
+            Err(e) => Err(e)
+          }
         },
         None => {
           if &tokens[*index].token_type != &TokenType::Semicolon {
@@ -111,6 +125,64 @@ impl StmtParser {
     }
   }
 
+  fn import_declaration(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Statement, ParserError> {
+    *index += 1;
+
+    let path = match ParserUtils::match_advance(tokens, index, &[TokenType::String]) {
+      Some(path) => path,
+      None => return Err(ParserError::ExpectExpression(String::from("Expected module path after 'import'.")))
+    };
+
+    match ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
+      Some(as_token) if as_token.lexeme == "as" => {},
+      _ => return Err(ParserError::ExpectExpression(String::from("Expected 'as' after module path.")))
+    }
+
+    let alias = match ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
+      Some(alias) => alias,
+      None => return Err(ParserError::ExpectExpression(String::from("Expected module name after 'as'.")))
+    };
+
+    if tokens[*index].token_type != TokenType::Semicolon {
+      return Err(ParserError::MissingToken(TokenType::Semicolon));
+    }
+    *index += 1;
+
+    Ok(Statement::Import(path, alias))
+  }
+
+  fn import_from_declaration(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Statement, ParserError> {
+    *index += 1;
+
+    let path = match ParserUtils::match_advance(tokens, index, &[TokenType::String]) {
+      Some(path) => path,
+      None => return Err(ParserError::ExpectExpression(String::from("Expected module path after 'from'.")))
+    };
+
+    if ParserUtils::match_advance(tokens, index, &[TokenType::Import]).is_none() {
+      return Err(ParserError::MissingToken(TokenType::Import));
+    }
+
+    let mut names: Vec<Rc<Token>> = Vec::new();
+    loop {
+      match ParserUtils::match_advance(tokens, index, &[TokenType::Identifier]) {
+        Some(name) => names.push(name),
+        None => return Err(ParserError::MissingToken(TokenType::Identifier))
+      }
+
+      if ParserUtils::match_advance(tokens, index, &[TokenType::Comma]).is_none() {
+        break;
+      }
+    }
+
+    if tokens[*index].token_type != TokenType::Semicolon {
+      return Err(ParserError::MissingToken(TokenType::Semicolon));
+    }
+    *index += 1;
+
+    Ok(Statement::ImportFrom(path, names))
+  }
+
   fn statement(tokens: &Vec<Rc<Token>>, index: &mut usize) -> Result<Statement, ParserError> {
     match tokens[*index].token_type {
       TokenType::EOL | TokenType::EOF => {
@@ -221,11 +293,7 @@ impl StmtParser {
                 match StmtParser::expression(tokens, index, &Some(TokenType::RightParen), true) {
                   Ok(increment) => {
                     match StmtParser::statement(tokens, index) {
-                      Ok(statement) => {
-                        let while_block = Statement::Block(Box::new(vec![statement, Statement::Expression(Box::new(increment))]));
-                        let statements: Vec<Statement> = vec![decl_or_stmt, Statement::While(Box::new(condition), Box::new(while_block))];
-                        Ok(Statement::Block(Box::new(statements)))
-                      },
+                      Ok(statement) => Ok(Statement::For(Box::new(decl_or_stmt), Box::new(condition), Box::new(increment), Box::new(statement))),
                       Err(e) => Err(e)
                     }
                   },
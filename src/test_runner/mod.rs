@@ -0,0 +1,162 @@
+pub mod test_report;
+
+use std::{cell::RefCell, fs, io, path::{Path, PathBuf}, rc::Rc, time::{Duration, Instant}};
+
+use crate::environment::Environment;
+use crate::module::ModuleLoader;
+use crate::native::Natives;
+use crate::parser::Parser;
+use crate::runtime::garbage_collector::GarbageCollector;
+use crate::runtime::runtime_error::RuntimeError;
+use crate::runtime::runtime_hook::{RuntimeHook, RuntimeHooks};
+use crate::runtime::runtime_type::RuntimeType;
+use crate::statement::{evaluator::StmtEvaluator, Statement};
+use crate::token::Token;
+
+pub struct TestFailure {
+  pub message: String,
+  pub line: Option<usize>
+}
+
+pub struct TestResult {
+  pub file: String,
+  pub name: String,
+  pub duration: Duration,
+  pub failure: Option<TestFailure>,
+  pub output: Vec<String>
+}
+
+// Collects `print` output so it can be attached to the test that produced it.
+#[derive(Clone)]
+struct OutputCapture {
+  lines: Rc<RefCell<Vec<String>>>
+}
+
+impl RuntimeHook for OutputCapture {
+  fn before_statement(&mut self, _statement: &Statement, _line: usize, _env: &Rc<RefCell<Environment>>) -> Result<(), RuntimeError> {
+    Ok(())
+  }
+
+  fn on_output(&mut self, text: &str) -> bool {
+    self.lines.borrow_mut().push(text.to_owned());
+    true
+  }
+}
+
+// Runs every top-level `test_*` function of the `.lox` files under a directory.
+pub struct TestRunner {
+  capture: OutputCapture
+}
+
+impl TestRunner {
+  pub fn new() -> Self {
+    let capture = OutputCapture { lines: Rc::new(RefCell::new(Vec::new())) };
+    RuntimeHooks::install(Box::new(capture.clone()));
+
+    Self { capture }
+  }
+
+  // Fails when the directory can't be read or holds no test, so a mistyped path doesn't pass as an empty run.
+  pub fn run(&self, dir: &str) -> Result<Vec<TestResult>, String> {
+    let mut files = Vec::new();
+    TestRunner::discover(Path::new(dir), &mut files).map_err(|e| format!("Failed to read {}: {}", dir, e))?;
+    files.sort();
+
+    let mut results = Vec::new();
+    for file in files {
+      results.extend(self.run_file(&file));
+    }
+
+    if results.is_empty() {
+      return Err(format!("No tests found in {}.", dir));
+    }
+    Ok(results)
+  }
+
+  fn discover(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_file() {
+      if path.extension().map(|e| e == "lox").unwrap_or(false) {
+        files.push(path.to_path_buf());
+      }
+      return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+      TestRunner::discover(&entry?.path(), files)?;
+    }
+
+    Ok(())
+  }
+
+  fn run_file(&self, path: &Path) -> Vec<TestResult> {
+    let file = path.display().to_string();
+    let start = Instant::now();
+
+    let statements = match TestRunner::load(path) {
+      Ok(statements) => statements,
+      Err(message) => return vec![TestResult {
+        file,
+        name: String::from("(load)"),
+        duration: start.elapsed(),
+        failure: Some(TestFailure { message, line: None }),
+        output: Vec::new()
+      }]
+    };
+
+    let tests: Vec<(String, usize)> = statements.iter()
+      .filter_map(|s| match s {
+        Statement::Function(name, args, _body, _captures) if name.lexeme.starts_with("test_") && args.is_empty() => Some((name.lexeme.to_string(), name.line)),
+        _ => None
+      })
+      .collect();
+
+    tests.into_iter()
+      .map(|(name, line)| {
+        let start = Instant::now();
+        ModuleLoader::push_file(&file);
+        let failure = TestRunner::run_test(&statements, &name, line).err().map(TestRunner::failure);
+        ModuleLoader::pop_file();
+        let output = self.capture.lines.borrow_mut().drain(..).collect();
+
+        TestResult { file: file.clone(), name, duration: start.elapsed(), failure, output }
+      })
+      .collect()
+  }
+
+  fn load(path: &Path) -> Result<Vec<Statement>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let (tokens, errors) = Token::tokenize(&source);
+    if !errors.is_empty() {
+      return Err(errors.join("\n"));
+    }
+
+    Parser::parse_statements(&tokens).map_err(|e| e.to_string())
+  }
+
+  // Each test gets a fresh global environment holding the natives and the file's top-level declarations and imports.
+  fn run_test(statements: &[Statement], name: &str, line: usize) -> Result<(), RuntimeError> {
+    let env = Rc::new(RefCell::new(Environment::global()));
+    Natives::define_all(&env);
+    let _globals = GarbageCollector::root_env(&env);
+
+    for statement in statements {
+      if let Statement::Function(_, _, _, _) | Statement::Var(_, _) | Statement::Import(_, _) | Statement::ImportFrom(_, _) = statement {
+        StmtEvaluator::evaluate(statement, &env)?;
+      }
+    }
+
+    let test = env.borrow().get(name);
+    match test.as_deref() {
+      Some(RuntimeType::Function(function)) => function.call(vec![], line, &env).map(|_| ()),
+      _ => Err(RuntimeError::UndefinedIdentifier(name.to_owned()))
+    }
+  }
+
+  fn failure(error: RuntimeError) -> TestFailure {
+    match error {
+      RuntimeError::AssertionFailed(message, line) => TestFailure { message, line: Some(line) },
+      _ => TestFailure { message: error.to_string(), line: None }
+    }
+  }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use super::TestResult;
+
+pub enum ReportFormat {
+  Pretty,
+  Tap,
+  Junit
+}
+
+impl ReportFormat {
+  pub fn parse(name: &str) -> Option<ReportFormat> {
+    match name {
+      "pretty" => Some(ReportFormat::Pretty),
+      "tap" => Some(ReportFormat::Tap),
+      "junit" => Some(ReportFormat::Junit),
+      _ => None
+    }
+  }
+}
+
+pub struct TestReport;
+
+impl TestReport {
+  pub fn render(results: &[TestResult], format: &ReportFormat) -> String {
+    match format {
+      ReportFormat::Pretty => TestReport::pretty(results),
+      ReportFormat::Tap => TestReport::tap(results),
+      ReportFormat::Junit => TestReport::junit(results)
+    }
+  }
+
+  fn pretty(results: &[TestResult]) -> String {
+    let mut report = String::new();
+    let mut current_file: Option<&str> = None;
+
+    for result in results {
+      if current_file != Some(&result.file) {
+        report.push_str(&format!("{}\n", result.file));
+        current_file = Some(&result.file);
+      }
+
+      let status = if result.failure.is_some() { "FAIL" } else { "ok  " };
+      report.push_str(&format!("  {} {} ({:.3} ms)\n", status, result.name, TestReport::millis(result.duration)));
+
+      if let Some(failure) = &result.failure {
+        for line in failure.message.lines() {
+          report.push_str(&format!("       {}\n", line));
+        }
+        if let Some(line) = failure.line {
+          report.push_str(&format!("       [line {}]\n", line));
+        }
+        for line in &result.output {
+          report.push_str(&format!("       | {}\n", line));
+        }
+      }
+    }
+
+    let failed = TestReport::failed(results);
+    report.push_str(&format!("\n{} passed, {} failed\n", results.len() - failed, failed));
+    report
+  }
+
+  fn tap(results: &[TestResult]) -> String {
+    let mut report = format!("TAP version 13\n1..{}\n", results.len());
+
+    for (i, result) in results.iter().enumerate() {
+      for line in &result.output {
+        report.push_str(&format!("# {}\n", line));
+      }
+
+      match &result.failure {
+        None => report.push_str(&format!("ok {} - {}: {}\n", i + 1, result.file, result.name)),
+        Some(failure) => {
+          report.push_str(&format!("not ok {} - {}: {}\n", i + 1, result.file, result.name));
+          report.push_str("  ---\n");
+          report.push_str(&format!("  message: \"{}\"\n", failure.message.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")));
+          if let Some(line) = failure.line {
+            report.push_str(&format!("  line: {}\n", line));
+          }
+          report.push_str("  ...\n");
+        }
+      }
+    }
+
+    report
+  }
+
+  fn junit(results: &[TestResult]) -> String {
+    let total: Duration = results.iter().map(|r| r.duration).sum();
+    let mut report = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    report.push_str(&format!("<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n", results.len(), TestReport::failed(results), total.as_secs_f64()));
+
+    let mut files: Vec<&str> = results.iter().map(|r| r.file.as_str()).collect();
+    files.dedup();
+
+    for file in files {
+      let suite: Vec<&TestResult> = results.iter().filter(|r| r.file == file).collect();
+      let failures = suite.iter().filter(|r| r.failure.is_some()).count();
+      let time: Duration = suite.iter().map(|r| r.duration).sum();
+
+      report.push_str(&format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n", TestReport::escape(file), suite.len(), failures, time.as_secs_f64()));
+
+      for result in suite {
+        report.push_str(&format!("    <testcase name=\"{}\" classname=\"{}\" time=\"{:.6}\">\n", TestReport::escape(&result.name), TestReport::escape(file), result.duration.as_secs_f64()));
+
+        if let Some(failure) = &result.failure {
+          let location = failure.line.map(|l| format!("line {}", l)).unwrap_or_default();
+          report.push_str(&format!("      <failure message=\"{}\">{}</failure>\n", TestReport::escape(&failure.message), location));
+        }
+        if !result.output.is_empty() {
+          report.push_str(&format!("      <system-out>{}</system-out>\n", TestReport::escape(&result.output.join("\n"))));
+        }
+
+        report.push_str("    </testcase>\n");
+      }
+
+      report.push_str("  </testsuite>\n");
+    }
+
+    report.push_str("</testsuites>\n");
+    report
+  }
+
+  pub fn failed(results: &[TestResult]) -> usize {
+    results.iter().filter(|r| r.failure.is_some()).count()
+  }
+
+  fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.
+  }
+
+  fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+  }
+}
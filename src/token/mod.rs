@@ -1,5 +1,12 @@
+#![allow(
+    clippy::len_zero,
+    clippy::upper_case_acronyms
+)]
+
 use std::{borrow::Cow, rc::Rc};
 
+use crate::runtime::{runtime_bigint::RuntimeBigInt, runtime_number::RuntimeNumber};
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
@@ -9,9 +16,18 @@ pub struct Token {
     pub line: usize
 }
 
+// A piece of an interpolated string: literal text, or the source of an embedded `${...}` expression and its line.
+pub enum StringPart {
+    Text(String),
+    Code(String, usize)
+}
+
 pub enum TokenizeError {
     UnexpectedCharacter(String),
-    UndeterminedString(String)
+    UndeterminedString(String),
+    // The message and the length of the whole literal, which is skipped.
+    InvalidEscape(String, usize),
+    InvalidNumber(String, usize)
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -20,10 +36,12 @@ pub enum TokenType {
     LeftParen, RightParen, LeftBrace, RightBrace, Comma, Dot, Plus, Star, Minus, Semicolon, Space, Tab,
     // *** One or Two Characters ***
     Less, LessEqual, Greater, GreaterEqual, Bang, BangEqual, Equal, EqualEqual, Slash, Comment,
+    Percent, StarStar, TildeSlash, PlusEqual, MinusEqual, StarEqual, SlashEqual, PlusPlus, MinusMinus,
+    Question, QuestionQuestion, Colon,
     // *** Reserved words ***
-    And, Class, Else, False, For, Fun, If, Nil, Or, Return, Super, This, True, Var, While, Print,
+    And, Class, Else, False, For, Fun, If, Nil, Or, Return, Super, This, True, Var, While, Print, Import,
     // *** Complex ***
-    String, Number, Identifier,
+    String, Interpolation, Number, BigInt, Identifier,
     // *** End ***
     EOL, EOF
 }
@@ -121,6 +139,55 @@ impl Token {
         Token { token_type: TokenType::Slash, name: "SLASH", lexeme: Cow::Borrowed("/"), literal: Cow::Borrowed("null"), line }
     }
 
+    pub fn t_percent(line: usize) -> Token {
+        Token { token_type: TokenType::Percent, name: "PERCENT", lexeme: Cow::Borrowed("%"), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_star_star(line: usize) -> Token {
+        Token { token_type: TokenType::StarStar, name: "STAR_STAR", lexeme: Cow::Borrowed("**"), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_plus_equal(line: usize) -> Token {
+        Token { token_type: TokenType::PlusEqual, name: "PLUS_EQUAL", lexeme: Cow::Borrowed("+="), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_minus_equal(line: usize) -> Token {
+        Token { token_type: TokenType::MinusEqual, name: "MINUS_EQUAL", lexeme: Cow::Borrowed("-="), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_star_equal(line: usize) -> Token {
+        Token { token_type: TokenType::StarEqual, name: "STAR_EQUAL", lexeme: Cow::Borrowed("*="), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_slash_equal(line: usize) -> Token {
+        Token { token_type: TokenType::SlashEqual, name: "SLASH_EQUAL", lexeme: Cow::Borrowed("/="), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_plus_plus(line: usize) -> Token {
+        Token { token_type: TokenType::PlusPlus, name: "PLUS_PLUS", lexeme: Cow::Borrowed("++"), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_minus_minus(line: usize) -> Token {
+        Token { token_type: TokenType::MinusMinus, name: "MINUS_MINUS", lexeme: Cow::Borrowed("--"), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_question(line: usize) -> Token {
+        Token { token_type: TokenType::Question, name: "QUESTION", lexeme: Cow::Borrowed("?"), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_question_question(line: usize) -> Token {
+        Token { token_type: TokenType::QuestionQuestion, name: "QUESTION_QUESTION", lexeme: Cow::Borrowed("??"), literal: Cow::Borrowed("null"), line }
+    }
+
+    pub fn t_colon(line: usize) -> Token {
+        Token { token_type: TokenType::Colon, name: "COLON", lexeme: Cow::Borrowed(":"), literal: Cow::Borrowed("null"), line }
+    }
+
+    // Floor division. `//` already starts a comment.
+    pub fn t_tilde_slash(line: usize) -> Token {
+        Token { token_type: TokenType::TildeSlash, name: "TILDE_SLASH", lexeme: Cow::Borrowed("~/"), literal: Cow::Borrowed("null"), line }
+    }
+
     pub fn t_comment(line: usize) -> Token {
         Token { token_type: TokenType::Comment, name: "COMMENT", lexeme: Cow::Borrowed("//"), literal: Cow::Borrowed("null"), line }
     }
@@ -190,15 +257,29 @@ impl Token {
         Token { token_type: TokenType::Print, name: "PRINT", lexeme: Cow::Borrowed("print"), literal: Cow::Borrowed("null"), line }
     }
 
+    pub fn t_import(line: usize) -> Token {
+        Token { token_type: TokenType::Import, name: "IMPORT", lexeme: Cow::Borrowed("import"), literal: Cow::Borrowed("null"), line }
+    }
+
     // *** Complex ***
     pub fn t_literal(lexeme: String, literal: String, line: usize) -> Token {
         Token { token_type: TokenType::String, name: "STRING", lexeme: Cow::Owned(lexeme), literal: Cow::Owned(literal), line }
     }
 
+    // A string containing `${...}`. The literal keeps the embedded expressions, see `string_parts`.
+    pub fn t_interpolation(lexeme: String, literal: String, line: usize) -> Token {
+        Token { token_type: TokenType::Interpolation, name: "STRING", lexeme: Cow::Owned(lexeme), literal: Cow::Owned(literal), line }
+    }
+
     pub fn t_number(lexeme: String, literal: String, line: usize) -> Token {
         Token { token_type: TokenType::Number, name: "NUMBER", lexeme: Cow::Owned(lexeme), literal: Cow::Owned(literal), line }
     }
 
+    // The literal holds the digits without the `n` suffix.
+    pub fn t_bigint(lexeme: String, literal: String, line: usize) -> Token {
+        Token { token_type: TokenType::BigInt, name: "BIGINT", lexeme: Cow::Owned(lexeme), literal: Cow::Owned(literal), line }
+    }
+
     pub fn t_identifier(lexeme: String, line: usize) -> Token {
         Token { token_type: TokenType::Identifier, name: "IDENTIFIER", lexeme: Cow::Owned(lexeme), literal: Cow::Borrowed("null"), line }
     }
@@ -247,7 +328,10 @@ impl Token {
                         },
                         _ => {
                             i += if token.lexeme.len() > 0 { token.lexeme.len() } else { 1 };
+                            // Multi-line strings.
+                            let newlines = token.lexeme.matches('\n').count();
                             tokens.push(Rc::new(token));
+                            line += newlines;
                         }
                     }
                 },
@@ -258,6 +342,12 @@ impl Token {
                             
                             i += 1;
                         },
+                        TokenizeError::InvalidEscape(msg, length) | TokenizeError::InvalidNumber(msg, length) => {
+                            errors.push(format!("[line {}] Error: {}", line, msg));
+
+                            line += bytes[i..i + length].iter().filter(|b| **b as char == '\n').count();
+                            i += length;
+                        },
                         TokenizeError::UndeterminedString(msg) => {
                             errors.push(format!("[line {}] Error: {}", line, msg));
 
@@ -286,18 +376,23 @@ impl Token {
             '}' => Ok(Token::t_right_brace(line)),
             ',' => Ok(Token::t_comma(line)),
             '.' => Ok(Token::t_dot(line)),
-            '+' => Ok(Token::t_plus(line)),
-            '*' => Ok(Token::t_star(line)),
-            '-' => Ok(Token::t_minus(line)),
+            '+' => Token::with_pair(bytes, index, Token::with_pair(bytes, index, Token::t_plus(line), Token::t_plus(line), Token::t_plus_plus(line))?, Token::t_equal(line), Token::t_plus_equal(line)),
+            '*' => Token::with_pair(bytes, index, Token::with_pair(bytes, index, Token::t_star(line), Token::t_star(line), Token::t_star_star(line))?, Token::t_equal(line), Token::t_star_equal(line)),
+            '%' => Ok(Token::t_percent(line)),
+            '?' => Token::with_pair(bytes, index, Token::t_question(line), Token::t_question(line), Token::t_question_question(line)),
+            ':' => Ok(Token::t_colon(line)),
+            '~' if bytes.get(index + 1) == Some(&b'/') => Ok(Token::t_tilde_slash(line)),
+            '-' => Token::with_pair(bytes, index, Token::with_pair(bytes, index, Token::t_minus(line), Token::t_minus(line), Token::t_minus_minus(line))?, Token::t_equal(line), Token::t_minus_equal(line)),
             ';' => Ok(Token::t_semicolon(line)),
             ' ' => Ok(Token::t_space(line)),
             '\t' => Ok(Token::t_tab(line)),
-            '/' => Token::with_pair(bytes, index, Token::t_slash(line), Token::t_slash(line), Token::t_comment(line)),
+            '/' => Token::with_pair(bytes, index, Token::with_pair(bytes, index, Token::t_slash(line), Token::t_slash(line), Token::t_comment(line))?, Token::t_equal(line), Token::t_slash_equal(line)),
             '<' => Token::with_pair(bytes, index, Token::t_less(line), Token::t_equal(line), Token::t_less_equal(line)),
             '>' => Token::with_pair(bytes, index, Token::t_greater(line), Token::t_equal(line), Token::t_greater_equal(line)),
             '!' => Token::with_pair(bytes, index, Token::t_bang(line), Token::t_equal(line), Token::t_bang_equal(line)),
             '=' => Token::with_pair(bytes, index, Token::t_equal(line), Token::t_equal(line), Token::t_equal_equal(line)),
             '"' => Token::with_literal(bytes, index, line),
+            'r' if bytes.get(index + 1) == Some(&b'"') => Token::with_literal(bytes, index, line),
             '0'..='9' => Token::with_number(bytes, index, line), 
             c if c.is_alphabetic() || c == '_' => Token::with_identifier(bytes, index, line),
             '\n' => Ok(Token::t_eol(line)),
@@ -333,61 +428,244 @@ impl Token {
                 else if s == "var" { Ok(Token::t_var(line)) }
                 else if s == "while" { Ok(Token::t_while(line)) }
                 else if s == "print" { Ok(Token::t_print(line)) }
+                else if s == "import" { Ok(Token::t_import(line)) }
                 else { Ok(Token::t_identifier(s.to_owned(), line)) },
             Err(_) => Ok(Token::t_eol(line))
         }
     }
 
+    // Decimal (`1_000.5`, `2.5e-3`), hexadecimal (`0xFF`) and binary (`0b1010`) literals. Underscores may separate
+    // digits.
     fn with_number(bytes: &[u8], index: usize, line: usize) -> Result<Token, TokenizeError> {
-        let mut i = index + 1;
-        let mut dots = 0;
+        let digits_end = |start: usize, radix: u32| {
+            let mut i = start;
+            while i < bytes.len() && ((bytes[i] as char).is_digit(radix) || bytes[i] as char == '_') {
+                i += 1;
+            }
+            i
+        };
+        let is_digit = |i: usize| i < bytes.len() && (bytes[i] as char).is_ascii_digit();
+
+        let prefix = bytes.get(index + 1).map(|b| (*b as char).to_ascii_lowercase());
+        let radix = match prefix {
+            Some('x') if bytes[index] as char == '0' => 16,
+            Some('b') if bytes[index] as char == '0' => 2,
+            _ => 10
+        };
+
+        let mut end;
+        if radix == 10 {
+            end = digits_end(index, 10);
+
+            // `123n` bigint literal.
+            let suffix_end = bytes.get(end + 1).map(|b| (*b as char).is_alphanumeric() || *b as char == '_').unwrap_or(false);
+            if bytes.get(end) == Some(&b'n') && !suffix_end {
+                let lexeme = String::from_utf8_lossy(&bytes[index..end + 1]).to_string();
+                return match RuntimeBigInt::parse(&lexeme[..lexeme.len() - 1]) {
+                    Some(value) if !lexeme.ends_with("_n") && !lexeme.contains("__") => Ok(Token::t_bigint(lexeme, value.to_string(), line)),
+                    _ => Err(TokenizeError::InvalidNumber(format!("Invalid number literal: {}.", lexeme), end + 1 - index))
+                };
+            }
+
+            if bytes.get(end) == Some(&b'.') && is_digit(end + 1) {
+                end = digits_end(end + 1, 10);
+            }
+            if matches!(bytes.get(end), Some(b'e') | Some(b'E')) {
+                let exponent = if matches!(bytes.get(end + 1), Some(b'+') | Some(b'-')) { end + 2 } else { end + 1 };
+                if is_digit(exponent) {
+                    end = digits_end(exponent, 10);
+                }
+            }
+        } else {
+            end = digits_end(index + 2, radix);
+        }
+
+        let lexeme = String::from_utf8_lossy(&bytes[index..end]).to_string();
+        let invalid = || TokenizeError::InvalidNumber(format!("Invalid number literal: {}.", lexeme), end - index);
+
+        // Underscores only between two digits.
+        let chars: Vec<char> = lexeme.chars().collect();
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '_' && !(i > 0 && chars[i - 1].is_digit(radix) && i + 1 < chars.len() && chars[i + 1].is_digit(radix)) {
+                return Err(invalid());
+            }
+        }
+
+        let digits = lexeme.replace('_', "");
+        let value = if radix == 10 {
+            digits.parse::<f64>().map_err(|_| invalid())?
+        } else {
+            u64::from_str_radix(&digits[2..], radix).map_err(|_| invalid())? as f64
+        };
+
+        Ok(Token::t_number(lexeme, RuntimeNumber::to_literal(value), line))
+    }
+
+    // `"..."`, `"""..."""` (may span lines) and their raw forms `r"..."` / `r"""..."""`, which keep backslashes and
+    // `${` as written.
+    fn with_literal(bytes: &[u8], index: usize, line: usize) -> Result<Token, TokenizeError> {
+        let raw = bytes[index] as char == 'r';
+        let quote = if raw { index + 1 } else { index };
+        let delimiter: &[u8] = if bytes[quote..].starts_with(b"\"\"\"") { b"\"\"\"" } else { b"\"" };
+        let content = quote + delimiter.len();
+
+        let mut literal: Vec<u8> = Vec::new();
+        let mut interpolated = false;
+        let mut invalid_escape: Option<String> = None;
+        let mut i = content;
         while i < bytes.len() {
-            let c = bytes[i] as char;
+            if bytes[i..].starts_with(delimiter) {
+                let end = i + delimiter.len();
+                let lexeme = String::from_utf8_lossy(&bytes[index..end]).to_string();
 
-            if c == '.' {
-                dots += 1;
-                if dots > 1 {
-                    break;
+                if let Some(msg) = invalid_escape {
+                    return Err(TokenizeError::InvalidEscape(msg, end - index));
                 }
-            } else if bytes[i] < 48 || bytes[i] > 57 {
-                break;
+                if interpolated {
+                    return Ok(Token::t_interpolation(lexeme, String::from_utf8_lossy(&bytes[content..i]).to_string(), line));
+                }
+                return Ok(Token::t_literal(lexeme, String::from_utf8_lossy(&literal).to_string(), line));
+            }
+
+            let c = bytes[i] as char;
+            if !raw && c == '\\' {
+                match Token::escape(bytes, i) {
+                    Ok((escaped, length)) => {
+                        literal.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                        i += length;
+                    },
+                    Err(msg) => {
+                        invalid_escape.get_or_insert(msg);
+                        i += 2;
+                    }
+                }
+                continue;
             }
 
+            if !raw && c == '$' && i + 1 < bytes.len() && bytes[i + 1] as char == '{' {
+                interpolated = true;
+                i = Token::skip_interpolation(bytes, i + 2, line)?;
+                continue;
+            }
+
+            literal.push(bytes[i]);
             i += 1;
         }
 
-        i -= 1;
+        Err(TokenizeError::UndeterminedString(String::from("Unterminated string.")))
+    }
 
-        if bytes[i] as char == '.' {
-            i -= 1;
-        }
+    // Decodes the escape sequence whose backslash is at `index`. Returns the char and the number of bytes read.
+    fn escape(bytes: &[u8], index: usize) -> Result<(char, usize), String> {
+        let c = match std::str::from_utf8(&bytes[index + 1..bytes.len().min(index + 5)]) {
+            Ok(s) => s.chars().next(),
+            Err(e) => String::from_utf8_lossy(&bytes[index + 1..index + 1 + e.valid_up_to().max(1)]).chars().next()
+        };
+
+        match c {
+            Some('n') => Ok(('\n', 2)),
+            Some('t') => Ok(('\t', 2)),
+            Some('r') => Ok(('\r', 2)),
+            Some('0') => Ok(('\0', 2)),
+            Some(c @ ('"' | '\\' | '$')) => Ok((c, 2)),
+            Some('u') => {
+                // \u{X} to \u{XXXXXX}
+                let digits = &bytes[bytes.len().min(index + 3)..bytes.len().min(index + 10)];
+                let close = match digits.iter().position(|b| *b as char == '}') {
+                    Some(close) if bytes.get(index + 2) == Some(&b'{') => close,
+                    _ => return Err(String::from("Invalid unicode escape sequence: expected \\u{...}."))
+                };
 
-        match std::str::from_utf8(&bytes[index..i + 1]) {
-            Ok(s) => {
-                let number: f64 = s.parse().unwrap();
-                let literal = if number.fract() == 0. { format!("{}.0", number) } else { number.to_string() };
-                Ok(Token::t_number(s.to_owned(), literal, line))
+                let hex = String::from_utf8_lossy(&digits[..close]).to_string();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) if close > 0 => Ok((c, close + 4)),
+                    _ => Err(format!("Invalid unicode escape sequence: \\u{{{}}}.", hex))
+                }
             },
-            Err(_) => Ok(Token::t_eol(line))
+            Some(c) => Err(format!("Invalid escape sequence: \\{}.", c)),
+            None => Err(String::from("Invalid escape sequence at end of string."))
         }
     }
 
-    fn with_literal(bytes: &[u8], index: usize, line: usize) -> Result<Token, TokenizeError> {
-        let mut i = index + 1;
+    // Replaces the escape sequences of a text already checked by `with_literal`.
+    fn unescape(text: &str) -> String {
+        let bytes = text.as_bytes();
+        let mut result: Vec<u8> = Vec::new();
+        let mut i = 0;
         while i < bytes.len() {
-            let c = bytes[i] as char;
+            if bytes[i] as char == '\\' {
+                if let Ok((escaped, length)) = Token::escape(bytes, i) {
+                    result.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                    i += length;
+                    continue;
+                }
+            }
+
+            result.push(bytes[i]);
+            i += 1;
+        }
 
-            if c == '"' {
-                match std::str::from_utf8(&bytes[index..i + 1]) {
-                    Ok(s) => return Ok(Token::t_literal(s.to_owned(), s[1..s.len() - 1].to_owned(), line)),
+        String::from_utf8_lossy(&result).to_string()
+    }
+
+    // Index just past the `}` that closes an embedded expression starting at `index`. Braces and strings inside the
+    // expression are skipped, so `"${f("}")}"` is one string.
+    fn skip_interpolation(bytes: &[u8], index: usize, line: usize) -> Result<usize, TokenizeError> {
+        let mut depth = 0;
+        let mut i = index;
+        while i < bytes.len() {
+            match bytes[i] as char {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(i + 1),
+                '}' => depth -= 1,
+                '"' => {
+                    i += Token::with_literal(bytes, i, line)?.lexeme.len();
+                    continue;
+                },
+                _ => {}
+            }
+            i += 1;
+        }
+
+        Err(TokenizeError::UndeterminedString(String::from("Unterminated string interpolation.")))
+    }
+
+    // Splits an interpolated string into its text and embedded expressions.
+    pub fn string_parts(&self) -> Vec<StringPart> {
+        let bytes = self.literal.as_bytes();
+        let mut parts: Vec<StringPart> = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] as char == '\\' {
+                i += 2;
+                continue;
+            }
+
+            if bytes[i] as char == '$' && i + 1 < bytes.len() && bytes[i + 1] as char == '{' {
+                let end = match Token::skip_interpolation(bytes, i + 2, self.line) {
+                    Ok(end) => end,
                     Err(_) => break
                 };
-            }
 
+                if start < i {
+                    parts.push(StringPart::Text(Token::unescape(&self.literal[start..i])));
+                }
+                let line = self.line + self.literal[..i].matches('\n').count();
+                parts.push(StringPart::Code(self.literal[i + 2..end - 1].to_owned(), line));
+
+                start = end;
+                i = end;
+                continue;
+            }
             i += 1;
         }
 
-        Err(TokenizeError::UndeterminedString(String::from("Unterminated string.")))
+        if start < bytes.len() {
+            parts.push(StringPart::Text(Token::unescape(&self.literal[start..])));
+        }
+
+        parts
     }
 
     fn with_pair(bytes: &[u8], index: usize, first: Token, second: Token, double: Token) -> Result<Token, TokenizeError> {
@@ -0,0 +1,32 @@
+// Each test crate uses only some of these.
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs the interpreter binary with the given arguments.
+pub fn interpreter(args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_interpreter-starter-rust"))
+    .args(args)
+    .output()
+    .expect("failed to run the interpreter")
+}
+
+// Same, with `input` on stdin.
+pub fn interpreter_with_input(args: &[&str], input: &str) -> Output {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_interpreter-starter-rust"))
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("failed to run the interpreter");
+
+  child.stdin.take().unwrap().write_all(input.as_bytes()).expect("failed to write the interpreter's input");
+  child.wait_with_output().expect("failed to run the interpreter")
+}
+
+// Absolute path of a file or directory under tests.
+pub fn fixture(path: &str) -> String {
+  format!("{}/tests/{}", env!("CARGO_MANIFEST_DIR"), path)
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use std::{env, fs, process};
+
+use common::{fixture, interpreter};
+
+#[test]
+fn writes_lcov() {
+  let program = fixture("coverage/program.lox");
+  let path = env::temp_dir().join(format!("lox-coverage-{}.info", process::id()));
+  let output = interpreter(&["run", &program, "--coverage", "--coverage-output", path.to_str().unwrap()]);
+  let summary = String::from_utf8_lossy(&output.stderr);
+
+  assert!(output.status.success(), "{}", summary);
+  assert_eq!("Lines: 8/10 (80.0%)\nBranches: 4/6 (66.7%)\nUncovered lines: 3, 9\n", summary);
+
+  let lcov = fs::read_to_string(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+
+  // The `if` on line 2 never takes its then branch; the `if` on line 14 never skips its body.
+  let expected = [
+    "TN:", &format!("SF:{}", program),
+    "BRDA:2,0,0,0", "BRDA:2,0,1,3", "BRDA:13,0,0,3", "BRDA:13,0,1,1", "BRDA:14,0,0,3", "BRDA:14,0,1,0",
+    "BRF:6", "BRH:4",
+    "DA:1,1", "DA:2,3", "DA:3,0", "DA:5,3", "DA:8,1", "DA:9,0", "DA:12,1", "DA:13,2", "DA:14,6", "DA:16,1",
+    "LF:10", "LH:8", "end_of_record"
+  ];
+  assert_eq!(expected.join("\n") + "\n", lcov);
+}
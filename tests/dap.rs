@@ -0,0 +1,149 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use common::fixture;
+
+// A DAP client speaking to `interpreter dap` over its stdio. Messages are checked as JSON text.
+struct Client {
+  child: Child,
+  output: BufReader<ChildStdout>,
+  seq: usize,
+  // Every event received so far, in order.
+  events: Vec<String>
+}
+
+impl Client {
+  fn start() -> Self {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_interpreter-starter-rust"))
+      .arg("dap")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()
+      .expect("failed to run the interpreter");
+    let output = BufReader::new(child.stdout.take().unwrap());
+
+    Self { child, output, seq: 0, events: Vec::new() }
+  }
+
+  // Sends a request and returns its response, collecting the events that come before it.
+  fn request(&mut self, command: &str, arguments: &str) -> String {
+    self.seq += 1;
+    let message = format!("{{\"seq\":{},\"type\":\"request\",\"command\":\"{}\",\"arguments\":{}}}", self.seq, command, arguments);
+    let stdin = self.child.stdin.as_mut().unwrap();
+    write!(stdin, "Content-Length: {}\r\n\r\n{}", message.len(), message).unwrap();
+    stdin.flush().unwrap();
+
+    let request_seq = format!("\"request_seq\":{}", self.seq);
+    self.receive_until(|message| message.contains(&request_seq))
+  }
+
+  fn event(&mut self, event: &str) -> String {
+    let event = format!("\"event\":\"{}\"", event);
+    if let Some(message) = self.events.iter().find(|message| message.contains(&event)) {
+      return message.clone();
+    }
+
+    self.receive_until(|message| message.contains(&event))
+  }
+
+  fn receive_until(&mut self, done: impl Fn(&str) -> bool) -> String {
+    loop {
+      let message = self.receive();
+      if done(&message) {
+        return message;
+      }
+      if message.contains("\"type\":\"event\"") {
+        self.events.push(message);
+      }
+    }
+  }
+
+  fn receive(&mut self) -> String {
+    let mut length = 0;
+    loop {
+      let mut header = String::new();
+      assert!(self.output.read_line(&mut header).unwrap() > 0, "the adapter closed its output");
+      if header.trim().is_empty() {
+        break;
+      }
+      if let Some(value) = header.strip_prefix("Content-Length:") {
+        length = value.trim().parse().unwrap();
+      }
+    }
+
+    let mut content = vec![0; length];
+    self.output.read_exact(&mut content).unwrap();
+    String::from_utf8(content).unwrap()
+  }
+}
+
+fn assert_contains(message: &str, expected: &str) {
+  assert!(message.contains(expected), "expected {} in: {}", expected, message);
+}
+
+#[test]
+fn breakpoint_session() {
+  let program = fixture("dap/program.lox");
+  let mut client = Client::start();
+
+  assert_contains(&client.request("initialize", "{}"), "\"supportsConfigurationDoneRequest\":true");
+  client.event("initialized");
+
+  let breakpoints = client.request("setBreakpoints", &format!("{{\"source\":{{\"path\":\"{}\"}},\"breakpoints\":[{{\"line\":2}}]}}", program));
+  assert_contains(&breakpoints, "\"verified\":true");
+  client.request("launch", &format!("{{\"program\":\"{}\"}}", program));
+  client.request("configurationDone", "{}");
+  assert_contains(&client.event("stopped"), "\"reason\":\"breakpoint\"");
+
+  let trace = client.request("stackTrace", "{\"threadId\":1}");
+  assert_contains(&trace, "\"name\":\"square\",\"line\":2");
+  assert_contains(&trace, "\"name\":\"<script>\",\"line\":9");
+
+  let scopes = client.request("scopes", "{\"frameId\":0}");
+  assert_contains(&scopes, "\"name\":\"Locals\",\"variablesReference\":1");
+  assert_contains(&client.request("variables", "{\"variablesReference\":1}"), "\"name\":\"n\",\"value\":\"3\"");
+
+  assert_contains(&client.request("evaluate", "{\"expression\":\"n * 2\",\"frameId\":0}"), "\"result\":\"6\"");
+  let rejected = client.request("evaluate", "{\"expression\":\"n = 1\",\"frameId\":0}");
+  assert_contains(&rejected, "\"success\":false");
+  assert_contains(&rejected, "not supported while debugging");
+
+  // Lists expand into their elements.
+  let items = client.request("evaluate", "{\"expression\":\"items\",\"frameId\":1}");
+  assert_contains(&items, "\"result\":\"[1, 2]\"");
+  let reference = items.split("\"variablesReference\":").nth(1).and_then(|r| r.split(|c: char| !c.is_ascii_digit()).next()).unwrap().to_owned();
+  let elements = client.request("variables", &format!("{{\"variablesReference\":{}}}", reference));
+  assert_contains(&elements, "\"name\":\"[1]\",\"value\":\"2\"");
+
+  client.request("continue", "{\"threadId\":1}");
+  assert_contains(&client.event("output"), "\"output\":\"9\\n\"");
+  assert_contains(&client.event("exited"), "\"exitCode\":0");
+  client.event("terminated");
+  client.request("disconnect", "{}");
+
+  assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn breakpoints_in_modules() {
+  let program = fixture("debugger/modules.lox");
+  let module = fixture("debugger/lib.lox");
+  let mut client = Client::start();
+
+  client.request("initialize", "{}");
+  client.request("setBreakpoints", &format!("{{\"source\":{{\"path\":\"{}\"}},\"breakpoints\":[{{\"line\":3}}]}}", module));
+  client.request("launch", &format!("{{\"program\":\"{}\"}}", program));
+  client.request("configurationDone", "{}");
+  client.event("stopped");
+
+  // The module's frame points at the module, its caller at the program.
+  let trace = client.request("stackTrace", "{\"threadId\":1}");
+  assert_contains(&trace, &format!("\"name\":\"twice\",\"line\":3,\"column\":1,\"source\":{{\"name\":\"lib.lox\",\"path\":\"{}\"}}", module));
+  assert_contains(&trace, &format!("\"name\":\"<script>\",\"line\":3,\"column\":1,\"source\":{{\"name\":\"modules.lox\",\"path\":\"{}\"}}", program));
+
+  client.request("continue", "{\"threadId\":1}");
+  assert_contains(&client.event("exited"), "\"exitCode\":0");
+  client.request("disconnect", "{}");
+}
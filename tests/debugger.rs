@@ -0,0 +1,65 @@
+mod common;
+
+use common::{fixture, interpreter_with_input};
+
+#[test]
+fn scripted_session() {
+  let session = "b 2\nc\np n\nbt\nn\nv\nbl\nd 2\nc\n";
+  let output = interpreter_with_input(&["debug", &fixture("debugger/program.lox")], session);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+
+  assert_eq!(Some(0), output.status.code(), "{}", stderr);
+  // The program's own output is untouched.
+  assert_eq!("9\n", String::from_utf8_lossy(&output.stdout));
+
+  for expected in [
+    "Stopped at line 1: fun square(n) {",
+    "Breakpoint set at line 2",
+    "Stopped at line 2: var result = n * n;",
+    "n = 3",
+    "#0 square at line 2\n#1 <script> at line 7",
+    "Stopped at line 3: return result;",
+    "Scope 0:\n  result = 9\nScope 1:\n  n = 3\nScope 2 (global):",
+    "line 2: var result = n * n;",
+    "Breakpoint removed from line 2"
+  ] {
+    assert!(stderr.contains(expected), "expected \"{}\" in: {}", expected, stderr);
+  }
+}
+
+#[test]
+fn finishes_when_input_ends() {
+  let output = interpreter_with_input(&["debug", &fixture("debugger/program.lox")], "");
+  assert_eq!(Some(0), output.status.code());
+  assert_eq!("9\n", String::from_utf8_lossy(&output.stdout));
+}
+
+// Breakpoints belong to a file: `b 2` in the program doesn't stop on line 2 of the module it imports.
+#[test]
+fn breakpoints_in_modules() {
+  let session = "b 2\nb lib.lox:3\nc\nc\nl\nc\n";
+  let output = interpreter_with_input(&["debug", &fixture("debugger/modules.lox")], session);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+
+  assert_eq!("2\n", String::from_utf8_lossy(&output.stdout));
+  for expected in [
+    "Breakpoint set at line 3 of lib.lox",
+    "Stopped at line 2: var a = 1;",
+    "Stopped at line 3 of lib.lox: return doubled;",
+    "->    3 |   return doubled;"
+  ] {
+    assert!(stderr.contains(expected), "expected \"{}\" in: {}", expected, stderr);
+  }
+  assert!(!stderr.contains("Stopped at line 2 of lib.lox"), "{}", stderr);
+}
+
+// A closure keeps the variables it uses, not the scope it was declared in.
+#[test]
+fn closures_see_only_captured_variables() {
+  let output = interpreter_with_input(&["debug", &fixture("debugger/closure.lox")], "b 5\nc\nv\nc\n");
+  let stderr = String::from_utf8_lossy(&output.stderr);
+
+  assert_eq!("1\n", String::from_utf8_lossy(&output.stdout));
+  assert!(stderr.contains("Scope 0:\nScope 1:\n  count = 0\nScope 2 (global):"), "{}", stderr);
+  assert!(!stderr.contains("unused"), "{}", stderr);
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::{fixture, interpreter};
+
+// Runs a script under tests/errors and checks it fails with `code` and `message` on stderr.
+fn assert_fails(script: &str, code: i32, message: &str) {
+  let output = interpreter(&["run", &fixture(&format!("errors/{}", script))]);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+
+  assert_eq!(Some(code), output.status.code(), "{}", stderr);
+  assert!(stderr.contains(message), "expected \"{}\" in: {}", message, stderr);
+}
+
+#[test]
+fn unterminated_interpolation() {
+  assert_fails("unterminated_interpolation.lox", 65, "[line 2] Error: Unterminated string interpolation.");
+}
+
+#[test]
+fn invalid_interpolated_expression() {
+  assert_fails("bad_interpolated_expression.lox", 65, "Expect expression.");
+}
+
+#[test]
+fn runtime_errors_in_interpolations_report_their_line() {
+  assert_fails("interpolated_runtime_error.lox", 70, "Operand must be a number.\n[line 3]");
+}
+
+#[test]
+fn invalid_escape() {
+  assert_fails("invalid_escape.lox", 65, "[line 2] Error: Invalid escape sequence: \\q.");
+}
+
+#[test]
+fn invalid_unicode_escape() {
+  assert_fails("invalid_unicode_escape.lox", 65, "Invalid unicode escape sequence: \\u{110000}.");
+  assert_fails("unbraced_unicode_escape.lox", 65, "Invalid unicode escape sequence: expected \\u{...}.");
+}
+
+#[test]
+fn lines_are_counted_inside_multi_line_strings() {
+  assert_fails("error_after_multi_line_string.lox", 70, "Operand must be a number.\n[line 4]");
+}
+
+#[test]
+fn invalid_assignment_targets() {
+  for script in ["invalid_compound_assignment.lox", "invalid_increment.lox"] {
+    let output = interpreter(&["run", &fixture(&format!("errors/{}", script))]);
+
+    assert_eq!(Some(65), output.status.code(), "{}", script);
+    assert!(output.stdout.is_empty(), "{}", script);
+  }
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::{fixture, interpreter};
+
+#[test]
+fn collects_cycles() {
+  let output = interpreter(&["run", &fixture("gc/cycles.lox"), "--gc-stats"]);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert_eq!(Some(0), output.status.code(), "{}", stderr);
+
+  // GC: <collections> collections, <freed> objects freed
+  let freed: usize = stderr.split(", ").nth(1)
+    .and_then(|part| part.split(' ').next())
+    .and_then(|count| count.parse().ok())
+    .unwrap_or_else(|| panic!("no GC stats in: {}", stderr));
+  assert!(freed > 0, "{}", stderr);
+}
+
+// Collections running in an imported module keep the scope of the function importing it.
+#[test]
+fn importer_survives_collections() {
+  let output = interpreter(&["run", &fixture("gc/import_in_function.lox"), "--gc-stats"]);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+
+  assert_eq!(Some(0), output.status.code(), "{}", stderr);
+  assert_eq!("kept\n", String::from_utf8_lossy(&output.stdout));
+}
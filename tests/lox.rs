@@ -0,0 +1,28 @@
+mod common;
+
+use common::{fixture, interpreter};
+
+// Runs the Lox test suite under tests/lox with the interpreter's own `test` command.
+#[test]
+fn lox_tests() {
+  let output = interpreter(&["test", &fixture("lox")]);
+
+  assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+}
+
+// A mistyped path must not pass as an empty run.
+#[test]
+fn missing_directory() {
+  let output = interpreter(&["test", &fixture("no_such_dir")]);
+
+  assert_eq!(Some(66), output.status.code());
+  assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to read"), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn directory_without_tests() {
+  let output = interpreter(&["test", &fixture("optimizer")]);
+
+  assert_eq!(Some(66), output.status.code());
+  assert!(String::from_utf8_lossy(&output.stderr).contains("No tests found"), "{}", String::from_utf8_lossy(&output.stderr));
+}
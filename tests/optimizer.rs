@@ -0,0 +1,28 @@
+mod common;
+
+use common::{fixture, interpreter};
+
+#[test]
+fn folds_constants_and_dead_branches() {
+  let output = interpreter(&["optimize", &fixture("optimizer/constants.lox")]);
+  let ast = String::from_utf8_lossy(&output.stdout);
+
+  assert!(ast.contains("Var: day, 86400.0"), "{}", ast);
+  assert!(ast.contains("Print: ab3c"), "{}", ast);
+  assert!(ast.contains("Print: (* (group (+ x 2.0)) 7.0)"), "{}", ast);
+  // Integers past 2^53 stay exact.
+  assert!(ast.contains("Print: 9007199254740993.0"), "{}", ast);
+  assert!(!ast.contains("never"), "{}", ast);
+  // Errors are left for the runtime.
+  assert!(ast.contains("Print: (- str)"), "{}", ast);
+}
+
+#[test]
+fn optimized_programs_behave_the_same() {
+  let unoptimized = interpreter(&["run", &fixture("optimizer/constants.lox"), "--opt-level", "0"]);
+  for level in ["1", "2"] {
+    let optimized = interpreter(&["run", &fixture("optimizer/constants.lox"), "--opt-level", level]);
+    assert_eq!(unoptimized.stdout, optimized.stdout);
+    assert_eq!(unoptimized.status.code(), optimized.status.code());
+  }
+}
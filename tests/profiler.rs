@@ -0,0 +1,44 @@
+mod common;
+
+use std::{env, fs, process};
+
+use common::{fixture, interpreter};
+
+// The whitespace separated columns of the report row starting with `label`.
+fn row<'a>(report: &'a str, label: &str) -> Vec<&'a str> {
+  report.lines()
+    .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+    .find(|columns| columns.first() == Some(&label))
+    .unwrap_or_else(|| panic!("no row for {} in: {}", label, report))
+}
+
+#[test]
+fn counts_calls_and_lines() {
+  let output = interpreter(&["run", &fixture("profiler/program.lox"), "--profile"]);
+  let report = String::from_utf8_lossy(&output.stderr);
+
+  assert_eq!("5050\n3\n", String::from_utf8_lossy(&output.stdout));
+  assert_eq!("1", row(&report, "work")[1]);
+  assert_eq!("100", row(&report, "leaf")[1]);
+  assert_eq!(vec!["len", "(native)", "1"], row(&report, "len")[..3]);
+  // Line 7 holds the loop, its initializer and the body, which runs 100 times.
+  assert_eq!(vec!["7", "102"], row(&report, "7"));
+  assert_eq!(vec!["2", "100"], row(&report, "2"));
+}
+
+#[test]
+fn writes_collapsed_stacks() {
+  let path = env::temp_dir().join(format!("lox-profile-{}.folded", process::id()));
+  let output = interpreter(&["run", &fixture("profiler/program.lox"), "--profile", "--profile-output", path.to_str().unwrap()]);
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+  let collapsed = fs::read_to_string(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+
+  let stacks: Vec<&str> = collapsed.lines().map(|line| {
+    let (stack, micros) = line.rsplit_once(' ').unwrap();
+    assert!(micros.parse::<u64>().is_ok(), "{}", line);
+    stack
+  }).collect();
+  assert_eq!(vec!["<script>", "<script>;len", "<script>;work", "<script>;work;leaf"], stacks);
+}
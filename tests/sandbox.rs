@@ -0,0 +1,61 @@
+mod common;
+
+use std::process::Output;
+
+use common::{fixture, interpreter};
+
+// Runs a script under tests/sandbox with the given interpreter options.
+fn run(script: &str, options: &[&str]) -> Output {
+  let path = fixture(&format!("sandbox/{}", script));
+  interpreter(&[&["run", &path], options].concat())
+}
+
+fn assert_fails_with(output: Output, message: &str) {
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert_eq!(Some(70), output.status.code(), "{}", stderr);
+  assert!(stderr.contains(message), "expected \"{}\" in: {}", message, stderr);
+}
+
+#[test]
+fn step_limit() {
+  assert_fails_with(run("infinite_loop.lox", &["--max-steps", "1000"]), "Step limit of 1000 exceeded.");
+}
+
+#[test]
+fn timeout() {
+  assert_fails_with(run("infinite_loop.lox", &["--timeout-ms", "100"]), "Time limit of 100 ms exceeded.");
+}
+
+#[test]
+fn string_length_limit() {
+  assert_fails_with(run("string_growth.lox", &["--max-string-length", "4096"]), "String length limit of 4096 exceeded.");
+}
+
+#[test]
+fn allocation_limit() {
+  assert_fails_with(run("list_growth.lox", &["--max-allocations", "1000"]), "Allocation limit of 1000 exceeded.");
+}
+
+#[test]
+fn bigint_size_limit() {
+  assert_fails_with(run("bigint_growth.lox", &["--max-string-length", "4096"]), "String length limit of 4096 exceeded.");
+}
+
+#[test]
+fn timeout_inside_a_bigint_product() {
+  // The last product alone takes longer than the time limit.
+  assert_fails_with(run("bigint_growth.lox", &["--timeout-ms", "100"]), "Time limit of 100 ms exceeded.");
+}
+
+#[test]
+fn capabilities() {
+  assert_fails_with(run("read_file.lox", &["--sandbox"]), "'read_file' needs the 'io' capability");
+  assert_fails_with(run("read_file.lox", &["--sandbox", "--allow", "clock"]), "'read_file' needs the 'io' capability");
+}
+
+#[test]
+fn limits_cannot_be_caught() {
+  let output = run("catch_denied.lox", &["--sandbox"]);
+  assert!(String::from_utf8_lossy(&output.stdout).is_empty());
+  assert_fails_with(output, "'clock' needs the 'clock' capability");
+}